@@ -1,4 +1,4 @@
-use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 
 fn bench_parse(c: &mut Criterion) {
     let inputs = [
@@ -18,6 +18,14 @@ fn bench_parse(c: &mut Criterion) {
             "without_structured_data_long_msg",
             r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [] BOMAn application event log entryaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb ccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc ddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff ggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggggg..."#,
         ),
+        (
+            "with_long_structured_data",
+            r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011" requestUri="/api/v1/namespaces/kube-system/configmaps/cluster-autoscaler-status?timeout=30s&resourceVersion=0" userAgent="cluster-autoscaler/v1.28.2 (linux/amd64) kubernetes/f0b1c3e/leader-election" sourceIPs="10.128.0.15,2600:1900:4000:9d2c:8000:0:0:0"][gcp@11129 resourceName="projects/my-production-project-1234/zones/us-central1-a/instances/gke-prod-cluster-default-pool-7c3a5e1f-x9kq"] BOMAn application event log entry"#,
+        ),
+        (
+            "with_long_hostname",
+            r#"<165>1 2003-10-11T22:14:15.003Z gke-prod-cluster-default-pool-7c3a5e1f-x9kq.us-central1-a.c.my-production-project-1234.internal kube-apiserver-audit-log-forwarder 8710 ID47 - BOMAn application event log entry"#,
+        ),
     ];
 
     let mut group = c.benchmark_group("parse");
@@ -36,10 +44,66 @@ fn bench_parse(c: &mut Criterion) {
                 syslog::rfc5424::parse_message(input).unwrap();
             });
         });
+
+        group.bench_with_input(BenchmarkId::new("rfc5424_lazy", name), input, |b, input| {
+            let input = input.as_bytes();
+
+            b.iter(|| {
+                syslog::rfc5424::parse_message_lazy(input).unwrap();
+            });
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("rfc5424_scratch", name),
+            input,
+            |b, input| {
+                let input = input.as_bytes();
+                let mut scratch = syslog::Scratch::new();
+
+                b.iter(|| {
+                    syslog::rfc5424::parse_message_in(input, &mut scratch).unwrap();
+                });
+            },
+        );
     }
 
     group.finish();
 }
 
-criterion_group!(benches, bench_parse);
+fn bench_batch(c: &mut Criterion) {
+    let inputs = [
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"] BOMAn application event log entry"#.as_bytes(),
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"] BOMAn application event log entry"#.as_bytes(),
+        r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1" sysUpTime="37" language="EN"][origin ip="192.168.0.1" software="test"] i am foobar"#.as_bytes(),
+        r#"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - BOM'su root' failed for lonvick on /dev/pts/8"#.as_bytes(),
+    ]
+    .repeat(8);
+
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(inputs.len() as u64));
+
+    group.bench_function("parse_message", |b| {
+        let mut out = Vec::with_capacity(inputs.len());
+
+        b.iter(|| {
+            out.clear();
+            for input in &inputs {
+                out.push(syslog::rfc5424::parse_message(input));
+            }
+        });
+    });
+
+    group.bench_function("batch_parser", |b| {
+        let mut parser = syslog::rfc5424::BatchParser::new();
+        let mut out = Vec::with_capacity(inputs.len());
+
+        b.iter(|| {
+            parser.parse_batch(&inputs, &mut out);
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_batch);
 criterion_main!(benches);
@@ -13,6 +13,14 @@ fn parse_timestamp(c: &mut Criterion) {
         })
     });
 
+    group.bench_function("builtin", |b| {
+        let input = input.as_bytes();
+
+        b.iter(|| {
+            let _ = syslog::timestamp::parse(input);
+        })
+    });
+
     group.bench_function("chrono", |b| {
         b.iter(|| {
             let _ = chrono::DateTime::parse_from_rfc3339(input);
@@ -43,5 +51,29 @@ fn parse_timestamp(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, parse_timestamp);
+fn format_timestamp(c: &mut Criterion) {
+    let input = r#"2023-04-07T12:52:00.654321+08:00"#;
+    let mut group = c.benchmark_group("format");
+
+    group.bench_function("builtin", |b| {
+        let (ts, _) = syslog::timestamp::parse(input.as_bytes()).unwrap();
+        let mut buf = [0u8; syslog::timestamp::MAX_LEN];
+
+        b.iter(|| {
+            let _ = syslog::timestamp::format_rfc3339(&ts, 6, &mut buf);
+        })
+    });
+
+    group.bench_function("chrono", |b| {
+        let datetime = chrono::DateTime::parse_from_rfc3339(input).unwrap();
+
+        b.iter(|| {
+            let _ = datetime.to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, parse_timestamp, format_timestamp);
 criterion_main!(benches);
@@ -0,0 +1,378 @@
+//! Parser for Linux audit records, as forwarded to syslog by `audisp-syslog`
+//! or the kernel, usually with facility `AUDIT` or `AUTHPRIV`.
+//!
+//! ```
+//! use syslog::audit;
+//!
+//! let lines = [
+//!     r#"type=SYSCALL msg=audit(1364481363.243:24287): arch=c000003e syscall=2 success=no exit=-13 pid=3538 uid=1000 comm="cat" exe="/usr/bin/cat" key="sshd_config""#,
+//!     r#"type=CWD msg=audit(1364481363.243:24287): cwd="/home/shadowman""#,
+//!     r#"type=PROCTITLE msg=audit(1364481363.243:24287): proctitle=636174002F6574632F7373682F737368645F636F6E666967"#,
+//! ];
+//! let records = lines.iter().map(|line| audit::parse(line)).collect::<Result<Vec<_>, _>>().unwrap();
+//!
+//! let events = audit::group(records);
+//! assert_eq!(events.len(), 1);
+//! assert_eq!(events[0].serial, 24287);
+//! assert_eq!(events[0].get("comm"), Some("cat"));
+//! assert_eq!(events[0].get("proctitle"), Some("cat /etc/ssh/sshd_config"));
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use chrono::{DateTime, FixedOffset};
+
+use crate::Error;
+
+/// Fields whose values are hex encoded when they contain spaces, quotes or
+/// control characters, and quoted otherwise.
+const ENCODED_FIELDS: &[&str] = &[
+    "acct",
+    "cmd",
+    "comm",
+    "cwd",
+    "data",
+    "dir",
+    "exe",
+    "file",
+    "key",
+    "name",
+    "path",
+    "proctitle",
+    "vm",
+];
+
+/// A single audit record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// The `node=` prefix added by audispd for remote nodes
+    pub node: Option<&'a str>,
+    /// E.g. `SYSCALL`, `PATH` or `USER_LOGIN`
+    pub record_type: &'a str,
+    /// From the `audit(SECONDS.MILLIS:SERIAL)` header, in UTC.
+    pub timestamp: DateTime<FixedOffset>,
+    /// Shared by all records of the same event
+    pub serial: u64,
+    /// The fields in their original order, with quotes stripped and hex
+    /// encoded values decoded.
+    pub fields: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl<'a> Record<'a> {
+    /// Returns the value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_ref())
+    }
+}
+
+/// The records of one audit event, e.g. a `SYSCALL` record followed by its
+/// `CWD`, `PATH` and `PROCTITLE` records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event<'a> {
+    pub node: Option<&'a str>,
+    pub timestamp: DateTime<FixedOffset>,
+    pub serial: u64,
+    /// The records in the order they were received
+    pub records: Vec<Record<'a>>,
+}
+
+impl<'a> Event<'a> {
+    /// Returns the first record of type `record_type`.
+    pub fn record(&self, record_type: &str) -> Option<&Record<'a>> {
+        self.records
+            .iter()
+            .find(|record| record.record_type == record_type)
+    }
+
+    /// Returns the value of the first field named `key` in any record.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.records.iter().find_map(|record| record.get(key))
+    }
+}
+
+/// Parse the audit record in `msg`.
+///
+/// Anything before `type=`, or `node=` if present, is ignored, e.g. the tag
+/// of BSD messages. The single quoted `msg='...'` field of userspace records
+/// like `USER_LOGIN` is flattened into the fields. Words without `=`, like
+/// in SELinux `AVC` records, are skipped, and so is the `\x1d` separator in
+/// front of the fields added by the `enriched` log format.
+///
+/// Hex encoded values of fields like `comm`, `exe` or `proctitle` are
+/// decoded, NUL bytes (e.g. between the arguments of `proctitle`) are
+/// replaced by spaces and invalid UTF-8 by `U+FFFD`.
+pub fn parse(msg: &str) -> Result<Record<'_>, Error> {
+    let type_pos = msg.find("type=").ok_or(Error::InvalidAudit)?;
+    let start = msg[..type_pos].rfind("node=").unwrap_or(type_pos);
+
+    let (prefix, rest) = msg[start..]
+        .split_once("msg=audit(")
+        .ok_or(Error::InvalidAudit)?;
+    let (header, rest) = rest.split_once("):").ok_or(Error::InvalidAudit)?;
+
+    let mut node = None;
+    let mut record_type = None;
+    for (key, value) in prefix.split_whitespace().filter_map(|f| f.split_once('=')) {
+        match key {
+            "node" => node = Some(value),
+            "type" => record_type = Some(value),
+            _ => {}
+        }
+    }
+    let record_type = record_type
+        .filter(|t| !t.is_empty())
+        .ok_or(Error::InvalidAudit)?;
+
+    let (timestamp, serial) = header.split_once(':').ok_or(Error::InvalidAudit)?;
+    let serial = serial.parse::<u64>().map_err(|_| Error::InvalidAudit)?;
+    let timestamp = parse_timestamp(timestamp).ok_or(Error::InvalidAudit)?;
+
+    let mut fields = Vec::new();
+    parse_fields(rest, &mut fields)?;
+
+    Ok(Record {
+        node,
+        record_type,
+        timestamp,
+        serial,
+        fields,
+    })
+}
+
+/// Group records into events, by their node, timestamp and serial.
+///
+/// Events are returned in the order of their first record, records from
+/// different events may be interleaved in the input.
+pub fn group<'a, I>(records: I) -> Vec<Event<'a>>
+where
+    I: IntoIterator<Item = Record<'a>>,
+{
+    let mut events: Vec<Event<'a>> = Vec::new();
+    let mut index = BTreeMap::<_, usize>::new();
+
+    for record in records {
+        let key = (record.node, record.timestamp, record.serial);
+        match index.get(&key) {
+            Some(&i) => events[i].records.push(record),
+            None => {
+                index.insert(key, events.len());
+                events.push(Event {
+                    node: record.node,
+                    timestamp: record.timestamp,
+                    serial: record.serial,
+                    records: alloc::vec![record],
+                });
+            }
+        }
+    }
+
+    events
+}
+
+/// Parse `SECONDS.MILLIS`
+fn parse_timestamp(s: &str) -> Option<DateTime<FixedOffset>> {
+    let (seconds, millis) = s.split_once('.')?;
+    if millis.len() != 3 || !millis.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let seconds = seconds.parse::<i64>().ok()?;
+    let millis = millis.parse::<u32>().ok()?;
+
+    DateTime::from_timestamp(seconds, millis * 1_000_000).map(|ts| ts.fixed_offset())
+}
+
+fn parse_fields<'a>(
+    mut rest: &'a str,
+    fields: &mut Vec<(&'a str, Cow<'a, str>)>,
+) -> Result<(), Error> {
+    let is_separator = |ch: char| ch.is_ascii_whitespace() || ch == '\x1d';
+
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            return Ok(());
+        }
+
+        let word_end = rest.find(is_separator).unwrap_or(rest.len());
+        let Some((key, _)) = rest[..word_end]
+            .split_once('=')
+            .filter(|(key, _)| !key.is_empty())
+        else {
+            rest = &rest[word_end..];
+            continue;
+        };
+        let value = &rest[key.len() + 1..];
+
+        match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let (quoted, after) = value[1..].split_once(quote).ok_or(Error::InvalidAudit)?;
+                rest = after;
+                if quote == '\'' && key == "msg" {
+                    parse_fields(quoted, fields)?;
+                } else {
+                    fields.push((key, Cow::Borrowed(quoted)));
+                }
+            }
+            _ => {
+                let end = value.find(is_separator).unwrap_or(value.len());
+                rest = &value[end..];
+                let value = &value[..end];
+                let value = match ENCODED_FIELDS.contains(&key) {
+                    true => decode_hex(value).map_or(Cow::Borrowed(value), Cow::Owned),
+                    false => Cow::Borrowed(value),
+                };
+                fields.push((key, value));
+            }
+        }
+    }
+}
+
+/// Decode an unquoted value of an encoded field, `None` if it is not hex,
+/// e.g. `(null)` or `?`.
+fn decode_hex(value: &str) -> Option<String> {
+    if value.is_empty() || !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    let bytes = value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            match (hi << 4 | lo) as u8 {
+                0 => Some(b' '),
+                b => Some(b),
+            }
+        })
+        .collect::<Option<Vec<u8>>>()?;
+
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let record = parse(r#"type=SYSCALL msg=audit(1364481363.243:24287): arch=c000003e syscall=2 success=no exit=-13 a0=7fffd19c5592 ppid=2686 pid=3538 auid=1000 uid=1000 tty=pts0 ses=1 comm="cat" exe="/usr/bin/cat" subj=unconfined_u:unconfined_r:unconfined_t:s0-s0:c0.c1023 key="sshd_config""#).unwrap();
+        assert_eq!(record.node, None);
+        assert_eq!(record.record_type, "SYSCALL");
+        assert_eq!(record.serial, 24287);
+        assert_eq!(
+            record.timestamp.to_rfc3339(),
+            "2013-03-28T14:36:03.243+00:00"
+        );
+        assert_eq!(record.fields.len(), 15);
+        assert_eq!(record.get("arch"), Some("c000003e"));
+        assert_eq!(record.get("a0"), Some("7fffd19c5592"));
+        assert_eq!(record.get("comm"), Some("cat"));
+        assert_eq!(record.get("key"), Some("sshd_config"));
+        assert_eq!(
+            record.get("subj"),
+            Some("unconfined_u:unconfined_r:unconfined_t:s0-s0:c0.c1023")
+        );
+
+        // forwarded by audispd, with the inner msg flattened
+        let record = parse(r#"audispd: node=web01 type=USER_LOGIN msg=audit(1680871920.654:412): pid=8449 uid=0 auid=1000 ses=3 msg='op=login id=1000 exe="/usr/sbin/sshd" hostname=? addr=10.0.0.1 terminal=/dev/pts/1 res=success'"#).unwrap();
+        assert_eq!(record.node, Some("web01"));
+        assert_eq!(record.record_type, "USER_LOGIN");
+        assert_eq!(record.get("msg"), None);
+        assert_eq!(record.get("exe"), Some("/usr/sbin/sshd"));
+        assert_eq!(record.get("hostname"), Some("?"));
+        assert_eq!(record.get("res"), Some("success"));
+
+        // free text in AVC records, and enriched fields
+        let record = parse("type=AVC msg=audit(1226270358.848:238): avc:  denied  { write } for  pid=13349 comm=\"certwatch\" name=\"cache\" dev=dm-0 ino=218171\x1dAUID=\"unset\" UID=\"root\"").unwrap();
+        assert_eq!(
+            record.fields.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            ["pid", "comm", "name", "dev", "ino", "AUID", "UID"]
+        );
+        assert_eq!(record.get("UID"), Some("root"));
+
+        let record = parse("type=EOE msg=audit(1364481363.243:24287): ").unwrap();
+        assert_eq!(record.record_type, "EOE");
+        assert!(record.fields.is_empty());
+    }
+
+    #[test]
+    fn hex_values() {
+        let record = parse(r#"type=PATH msg=audit(1364481363.243:24287): item=0 name=2F746D702F6D792066696C65 inode=409248 dev=fd:00 mode=0100644 nametype=NORMAL cap_fe=0 comm=(null) exe="/bin/sh""#).unwrap();
+        assert_eq!(record.get("name"), Some("/tmp/my file"));
+        assert_eq!(record.get("inode"), Some("409248"));
+        assert_eq!(record.get("mode"), Some("0100644"));
+        assert_eq!(record.get("comm"), Some("(null)"));
+        assert!(matches!(record.fields[1].1, Cow::Owned(_)));
+
+        // numbers which happen to be valid hex are kept as they are
+        let record = parse("type=CONFIG_CHANGE msg=audit(1364481363.243:24287): op=set audit_backlog_limit=8192 old=64 auid=1000 ses=1 res=1").unwrap();
+        assert_eq!(record.get("audit_backlog_limit"), Some("8192"));
+        assert_eq!(record.get("old"), Some("64"));
+
+        for (input, want) in [
+            ("636174002F6574632F706173737764", Some("cat /etc/passwd")),
+            ("6869FF", Some("hi\u{fffd}")),
+            ("abc", None),
+            ("zz", None),
+            ("", None),
+        ] {
+            assert_eq!(decode_hex(input).as_deref(), want, "{input}");
+        }
+    }
+
+    #[test]
+    fn grouping() {
+        let lines = [
+            "type=SYSCALL msg=audit(1364481363.243:24287): syscall=2 comm=\"cat\"",
+            "type=SYSCALL msg=audit(1364481363.250:24288): syscall=59 comm=\"ls\"",
+            "type=CWD msg=audit(1364481363.243:24287): cwd=\"/root\"",
+            "node=web02 type=SYSCALL msg=audit(1364481363.243:24287): syscall=2",
+            "type=EOE msg=audit(1364481363.243:24287):",
+            "type=EOE msg=audit(1364481363.250:24288):",
+        ];
+        let events = group(lines.iter().map(|line| parse(line).unwrap()));
+
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.node, e.serial, e.records.len()))
+                .collect::<Vec<_>>(),
+            [
+                (None, 24287, 3),
+                (None, 24288, 2),
+                (Some("web02"), 24287, 1)
+            ]
+        );
+        assert_eq!(events[0].record("CWD").unwrap().get("cwd"), Some("/root"));
+        assert_eq!(events[0].get("comm"), Some("cat"));
+        assert_eq!(events[1].get("comm"), Some("ls"));
+        assert!(events[1].record("CWD").is_none());
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            "",
+            "no audit record",
+            "type=SYSCALL arch=c000003e",
+            "msg=audit(1364481363.243:24287): missing type",
+            "type= msg=audit(1364481363.243:24287): empty type",
+            "type=SYSCALL msg=audit(1364481363.243:24287 missing colon",
+            "type=SYSCALL msg=audit(1364481363:24287): no millis",
+            "type=SYSCALL msg=audit(1364481363.243): no serial",
+            "type=SYSCALL msg=audit(x.243:24287): bad seconds",
+            "type=SYSCALL msg=audit(1364481363.243:-1): bad serial",
+            "type=SYSCALL msg=audit(1364481363.243:24287): comm=\"unterminated",
+            "type=USER_LOGIN msg=audit(1364481363.243:24287): msg='op=login",
+        ] {
+            assert_eq!(parse(input), Err(Error::InvalidAudit), "{input}");
+        }
+    }
+}
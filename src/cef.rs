@@ -0,0 +1,217 @@
+//! Parser for ArcSight [Common Event Format](https://www.microfocus.com/documentation/arcsight/arcsight-smartconnectors-8.3/cef-implementation-standard/)
+//! payloads, which are usually carried in the MSG part of a syslog message.
+//!
+//! ```
+//! let raw = br#"<134>1 2023-04-07T12:52:00Z fw01 - - - - CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|10|src=10.0.0.1 dst=2.1.2.2 spt=1232 msg=Detected a threat\=worm"#;
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let event = syslog::cef::parse(msg.msg).unwrap();
+//! assert_eq!(event.vendor, "Security");
+//! assert_eq!(event.signature_id, "100");
+//! assert_eq!(event.get("src"), Some("10.0.0.1"));
+//! assert_eq!(event.get("msg"), Some("Detected a threat=worm"));
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use crate::escape::{split_header, unescape, unescape_header};
+use crate::Error;
+
+/// A parsed CEF event, fields without escape sequences borrow from the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CefEvent<'a> {
+    pub version: u8,
+    pub vendor: Cow<'a, str>,
+    pub product: Cow<'a, str>,
+    pub device_version: Cow<'a, str>,
+    /// Device Event Class ID
+    pub signature_id: Cow<'a, str>,
+    pub name: Cow<'a, str>,
+    /// `0` to `10`, or `Low`, `Medium`, `High` and `Very-High`
+    pub severity: Cow<'a, str>,
+    /// The key=value pairs of the extension, in their original order.
+    pub extensions: Vec<(&'a str, Cow<'a, str>)>,
+}
+
+impl<'a> CefEvent<'a> {
+    /// Returns the value of the first extension named `key`.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.extensions
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v.as_ref())
+    }
+}
+
+/// Parse the CEF payload in `msg`.
+///
+/// Anything before `CEF:` is ignored, so this works on the MSG of RFC 5424
+/// messages as well as on BSD messages where the tag is still part of the
+/// message, e.g. `CEF:0|...` and `myapp: CEF:0|...`.
+///
+/// Header fields may escape `\|` and `\\`. Extension values may escape `\=`,
+/// `\\`, `\n` and `\r`, an unescaped `=` without a space before it is kept as
+/// part of the value, since many devices don't escape them in URLs.
+pub fn parse(msg: &str) -> Result<CefEvent<'_>, Error> {
+    let start = msg.find("CEF:").ok_or(Error::InvalidCef)? + 4;
+    let payload = &msg[start..];
+
+    let mut fields = [""; 7];
+    let rest = split_header(payload, &mut fields).ok_or(Error::InvalidCef)?;
+
+    let version = fields[0]
+        .trim()
+        .parse::<u8>()
+        .map_err(|_| Error::InvalidCef)?;
+
+    Ok(CefEvent {
+        version,
+        vendor: unescape_header(fields[1]),
+        product: unescape_header(fields[2]),
+        device_version: unescape_header(fields[3]),
+        signature_id: unescape_header(fields[4]),
+        name: unescape_header(fields[5]),
+        severity: unescape_header(fields[6]),
+        extensions: parse_extensions(rest)?,
+    })
+}
+
+fn parse_extensions(ext: &str) -> Result<Vec<(&str, Cow<'_, str>)>, Error> {
+    let bytes = ext.as_bytes();
+    let mut extensions = Vec::new();
+    let mut key: Option<&str> = None;
+    let mut value_start = 0;
+
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' => {
+                pos += 2;
+                continue;
+            }
+            b'=' => {
+                let key_start = match ext[value_start..pos].rfind(' ') {
+                    Some(space) => value_start + space + 1,
+                    // `=` in the middle of a value, e.g. `request=http://x?a=b`
+                    None if key.is_some() => {
+                        pos += 1;
+                        continue;
+                    }
+                    None => value_start,
+                };
+
+                match key {
+                    Some(key) => {
+                        let value = ext[value_start..key_start].trim_end();
+                        extensions.push((key, unescape_value(value)));
+                    }
+                    // garbage before the first key
+                    None if !ext[..key_start].trim().is_empty() => {
+                        return Err(Error::InvalidCef);
+                    }
+                    None => {}
+                }
+
+                if key_start == pos {
+                    return Err(Error::InvalidCef);
+                }
+
+                key = Some(&ext[key_start..pos]);
+                value_start = pos + 1;
+            }
+            _ => {}
+        }
+
+        pos += 1;
+    }
+
+    match key {
+        Some(key) => {
+            let value = ext[value_start..].trim_end();
+            extensions.push((key, unescape_value(value)));
+        }
+        None if !ext.trim().is_empty() => return Err(Error::InvalidCef),
+        None => {}
+    }
+
+    Ok(extensions)
+}
+
+fn unescape_value(s: &str) -> Cow<'_, str> {
+    unescape(s, |ch| match ch {
+        '=' | '\\' => Some(ch),
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let event =
+            parse(r"CEF:1|Vendor \| Inc|Prod\\uct|1.0|sig-1|Name with = sign|Very-High|").unwrap();
+        assert_eq!(event.version, 1);
+        assert_eq!(event.vendor, "Vendor | Inc");
+        assert!(matches!(event.vendor, Cow::Owned(_)));
+        assert_eq!(event.product, r"Prod\uct");
+        assert_eq!(event.device_version, "1.0");
+        assert!(matches!(event.device_version, Cow::Borrowed(_)));
+        assert_eq!(event.signature_id, "sig-1");
+        assert_eq!(event.name, "Name with = sign");
+        assert_eq!(event.severity, "Very-High");
+        assert!(event.extensions.is_empty());
+    }
+
+    #[test]
+    fn extensions() {
+        let event = parse(
+            r"Sep 19 08:26:10 host CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|10|src=10.0.0.1 msg=Detected a threat. No action needed  request=http://example.com/?a=b&c\=d cs1=line\nbreak\\ path=C:\\Windows cs1Label=",
+        )
+        .unwrap();
+
+        assert_eq!(
+            event
+                .extensions
+                .iter()
+                .map(|(key, value)| (*key, value.as_ref()))
+                .collect::<Vec<_>>(),
+            [
+                ("src", "10.0.0.1"),
+                ("msg", "Detected a threat. No action needed"),
+                ("request", "http://example.com/?a=b&c=d"),
+                ("cs1", "line\nbreak\\"),
+                ("path", r"C:\Windows"),
+                ("cs1Label", ""),
+            ]
+        );
+
+        // only values with escape sequences are copied
+        for (key, value) in &event.extensions {
+            let escaped = matches!(*key, "request" | "cs1" | "path");
+            assert_eq!(matches!(value, Cow::Owned(_)), escaped, "{key}");
+        }
+        assert_eq!(event.get("path"), Some(r"C:\Windows"));
+        assert_eq!(event.get("dst"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            "",
+            "not a cef message",
+            "CEF:0|Vendor|Product|1.0|100|Name",
+            r"CEF:0|Vendor|Product|1.0|100|Name|10\|",
+            "CEF:x|Vendor|Product|1.0|100|Name|10|",
+            "CEF:0|Vendor|Product|1.0|100|Name|10|garbage src=1",
+            "CEF:0|Vendor|Product|1.0|100|Name|10|src=1 =2",
+            "CEF:0|Vendor|Product|1.0|100|Name|10|garbage",
+        ] {
+            assert_eq!(parse(input), Err(Error::InvalidCef), "{input}");
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use core::fmt::Display;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -8,15 +8,37 @@ pub enum Error {
     ExpectedChar(char),
     ExpectSeparator,
     InvalidStructuredData,
+    InvalidHostname,
+    InvalidUtf8,
 
     InvalidTimestamp,
     OutOfRangeTimezone,
+
+    BufferTooSmall,
+
+    MessageTooLong,
+
+    InvalidFingerprint,
+
+    InvalidCef,
+    InvalidLeef,
+    InvalidKmsg,
+    InvalidJournal,
+    InvalidGelf,
+    InvalidAudit,
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+impl From<core::convert::Infallible> for Error {
+    fn from(err: core::convert::Infallible) -> Self {
+        match err {}
+    }
+}
+
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::BadSeverity => f.write_str("bad severity in message"),
             Error::BadFacility => f.write_str("bad facility in message"),
@@ -24,9 +46,24 @@ impl Display for Error {
             Error::ExpectedChar(c) => write!(f, "unexpected input at character {c}"),
             Error::ExpectSeparator => f.write_str("expect a separator"),
             Error::InvalidStructuredData => f.write_str("invalid structured data"),
+            Error::InvalidHostname => f.write_str("invalid hostname"),
+            Error::InvalidUtf8 => f.write_str("invalid UTF-8 in message"),
             // Timestamp
             Error::InvalidTimestamp => f.write_str("invalid timestamp"),
             Error::OutOfRangeTimezone => f.write_str("timezone offset is out of range"),
+            // Formatting
+            Error::BufferTooSmall => f.write_str("buffer is too small"),
+            // Server
+            Error::MessageTooLong => f.write_str("message exceeds the maximum length"),
+            // TLS
+            Error::InvalidFingerprint => f.write_str("invalid certificate fingerprint"),
+            // Payloads
+            Error::InvalidCef => f.write_str("invalid CEF payload"),
+            Error::InvalidLeef => f.write_str("invalid LEEF payload"),
+            Error::InvalidKmsg => f.write_str("invalid kmsg record"),
+            Error::InvalidJournal => f.write_str("invalid journal entry"),
+            Error::InvalidGelf => f.write_str("invalid GELF message"),
+            Error::InvalidAudit => f.write_str("invalid audit record"),
         }
     }
 }
@@ -0,0 +1,72 @@
+//! Backslash escapes shared by the payload formats, e.g. CEF, LEEF and
+//! key-value pairs.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+/// Fill `fields` with the `|` separated header fields of `payload`, skipping
+/// escaped pipes, and return what follows the last one.
+pub(crate) fn split_header<'a>(payload: &'a str, fields: &mut [&'a str]) -> Option<&'a str> {
+    let mut field_start = 0;
+    let mut count = 0;
+    let mut escaped = false;
+    for (pos, ch) in payload.bytes().enumerate() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match ch {
+            b'\\' => escaped = true,
+            b'|' => {
+                fields[count] = &payload[field_start..pos];
+                field_start = pos + 1;
+                count += 1;
+                if count == fields.len() {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (count == fields.len()).then(|| &payload[field_start..])
+}
+
+/// Unescape `\|` and `\\` in a header field.
+pub(crate) fn unescape_header(s: &str) -> Cow<'_, str> {
+    unescape(s, |ch| match ch {
+        '|' | '\\' => Some(ch),
+        _ => None,
+    })
+}
+
+/// Resolve the backslash escapes of `s`, `escape` maps the character after
+/// the backslash to its value. Unknown escape sequences are kept as they are.
+pub(crate) fn unescape(s: &str, escape: impl Fn(char) -> Option<char>) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some(next) => match escape(next) {
+                Some(unescaped) => out.push(unescaped),
+                None => {
+                    out.push('\\');
+                    out.push(next);
+                }
+            },
+            None => out.push('\\'),
+        }
+    }
+
+    Cow::Owned(out)
+}
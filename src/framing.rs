@@ -0,0 +1,275 @@
+//! Splitting byte streams into frames, for `stream::Messages` and the
+//! stream listeners of `server`.
+//!
+//! Newline-delimited and octet-counted (RFC 6587) framing can be mixed. A
+//! frame starting with at most 10 ASCII digits and a space is octet-counted
+//! (`MSG-LEN SP SYSLOG-MSG`), if `MSG-LEN` is within the maximum length.
+//! Anything else is read until the next `\n`, or `\0` as sent by `syslog(3)`
+//! to stream sockets. Empty lines are skipped.
+
+use std::ops::Range;
+
+use crate::Error;
+
+/// An octet count has at most this many digits, which is enough for any
+/// `max_len` that fits in memory.
+const MAX_DIGITS: usize = 10;
+
+/// Splits a byte stream into frames, the data is pushed with `extend` as it
+/// arrives.
+#[derive(Default)]
+pub(crate) struct Frames {
+    pub(crate) buf: Vec<u8>,
+    start: usize,
+    /// The number of newlines before `start`
+    newlines: usize,
+    /// The line the last frame started on
+    pub(crate) line: usize,
+    /// Whether the rest of a line which is too long is skipped
+    discarding: bool,
+}
+
+impl Frames {
+    pub(crate) fn extend(&mut self, data: &[u8]) {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+        self.buf.extend_from_slice(data);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.start == self.buf.len()
+    }
+
+    /// The next complete frame, `None` if more data is needed.
+    ///
+    /// A line which is too long fails with `Error::MessageTooLong`, and is
+    /// skipped up to its end.
+    pub(crate) fn next(&mut self, max_len: usize) -> Result<Option<Range<usize>>, Error> {
+        if self.discarding {
+            match find_end(&self.buf[self.start..]) {
+                Some(end) => {
+                    self.advance(end + 1);
+                    self.discarding = false;
+                }
+                None => {
+                    self.start = self.buf.len();
+                    return Ok(None);
+                }
+            }
+        }
+
+        // empty lines, and the newline some senders put after octet-counted
+        // frames
+        while matches!(self.buf.get(self.start), Some(b'\n' | b'\r' | b'\0')) {
+            self.advance(1);
+        }
+
+        let data = &self.buf[self.start..];
+        if data.is_empty() {
+            return Ok(None);
+        }
+        self.line = self.newlines + 1;
+
+        match header(data, max_len) {
+            Header::Octets { header, len } => {
+                if data.len() < header + len {
+                    return Ok(None);
+                }
+                let frame = self.start + header..self.start + header + len;
+                self.advance(header + len);
+                return Ok(Some(frame));
+            }
+            Header::Partial => return Ok(None),
+            Header::Line => {}
+        }
+
+        match find_end(data) {
+            Some(end) if end > max_len => {
+                self.advance(end + 1);
+                Err(Error::MessageTooLong)
+            }
+            Some(end) => {
+                let frame = self.start..self.start + trim_cr(&data[..end]);
+                self.advance(end + 1);
+                Ok(Some(frame))
+            }
+            None if data.len() > max_len => {
+                self.start = self.buf.len();
+                self.discarding = true;
+                Err(Error::MessageTooLong)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// The unterminated frame at the end of the stream, if it's not an
+    /// incomplete octet-counted one. All data is consumed.
+    pub(crate) fn rest(&mut self, max_len: usize) -> Option<Range<usize>> {
+        let data = &self.buf[self.start..];
+        let frame = match header(data, max_len) {
+            Header::Line => Some(self.start..self.start + trim_cr(data)),
+            _ => None,
+        };
+
+        self.line = self.newlines + 1;
+        self.advance(data.len());
+        frame
+    }
+
+    fn advance(&mut self, len: usize) {
+        let end = self.start + len;
+        self.newlines += self.buf[self.start..end]
+            .iter()
+            .filter(|ch| **ch == b'\n')
+            .count();
+        self.start = end;
+    }
+}
+
+/// How a frame starts, see `header`.
+enum Header {
+    /// `MSG-LEN SP`, `header` bytes long
+    Octets {
+        header: usize,
+        len: usize,
+    },
+    /// Only digits so far
+    Partial,
+    Line,
+}
+
+/// The octet count of an octet-counted frame, which is only taken as one if
+/// it's followed by a space and within `max_len`.
+fn header(data: &[u8], max_len: usize) -> Header {
+    let mut len = 0usize;
+    for (pos, &ch) in data.iter().enumerate().take(MAX_DIGITS + 1) {
+        match ch {
+            b'0'..=b'9' => {
+                len = match len
+                    .checked_mul(10)
+                    .and_then(|len| len.checked_add((ch - b'0') as usize))
+                {
+                    Some(len) => len,
+                    None => return Header::Line,
+                };
+            }
+            b' ' if pos > 0 && len <= max_len => {
+                return Header::Octets {
+                    header: pos + 1,
+                    len,
+                }
+            }
+            _ => return Header::Line,
+        }
+    }
+
+    match data.len() <= MAX_DIGITS {
+        true => Header::Partial,
+        false => Header::Line,
+    }
+}
+
+/// The position of the `\n` or `\0` terminating a line.
+fn find_end(data: &[u8]) -> Option<usize> {
+    data.iter().position(|ch| matches!(ch, b'\n' | b'\0'))
+}
+
+/// The length of `line` without a trailing `\r`.
+fn trim_cr(line: &[u8]) -> usize {
+    line.strip_suffix(b"\r").unwrap_or(line).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(chunks: &[&[u8]], max_len: usize) -> (Vec<String>, Vec<Error>) {
+        let mut frames = Frames::default();
+        let mut out = Vec::new();
+        let mut errs = Vec::new();
+        for chunk in chunks {
+            frames.extend(chunk);
+            loop {
+                match frames.next(max_len) {
+                    Ok(Some(frame)) => {
+                        out.push(String::from_utf8(frames.buf[frame].to_vec()).unwrap())
+                    }
+                    Ok(None) => break,
+                    Err(err) => errs.push(err),
+                }
+            }
+        }
+        if let Some(frame) = frames.rest(max_len) {
+            out.push(String::from_utf8(frames.buf[frame].to_vec()).unwrap());
+        }
+
+        (out, errs)
+    }
+
+    #[test]
+    fn framing() {
+        let (frames, errs) = split(
+            &[
+                b"<13>1 - - - - - first\r\n16 <13>1 - - -",
+                b" - - \n\n2",
+                b"6 <13>1 - - - - - multi\nline<13>1 - - - - - nul\0",
+                b"<13>1 - - - - - unterminated",
+            ],
+            1024,
+        );
+        assert_eq!(errs, []);
+        assert_eq!(
+            frames,
+            [
+                "<13>1 - - - - - first",
+                "<13>1 - - - - - ",
+                "<13>1 - - - - - multi\nline",
+                "<13>1 - - - - - nul",
+                "<13>1 - - - - - unterminated",
+            ]
+        );
+
+        // an incomplete octet-counted frame is dropped
+        assert_eq!(split(&[b"100 <13>1"], 1024), (vec![], vec![]));
+    }
+
+    #[test]
+    fn not_octet_counted() {
+        // the count is too long or too large, or not followed by a space
+        for input in [
+            &b"12345678901 <13>"[..],
+            b"99999999999999999999 <13>",
+            b"2000 <13>1",
+            b"12x4 <13>",
+            b"2024-01-01 foo",
+        ] {
+            let want = String::from_utf8(input.to_vec()).unwrap();
+            assert_eq!(split(&[input], 32), (vec![want], vec![]), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn too_long() {
+        for input in [
+            &b"<13>1 - - - - - no newline yet"[..],
+            b"<13>1 - - - - - too long\n",
+            b"99999999999999999999 <13>1 - - -",
+        ] {
+            assert_eq!(
+                split(&[input], 16),
+                (vec![], vec![Error::MessageTooLong]),
+                "{input:?}"
+            );
+        }
+
+        // the rest of the line is skipped, even if it arrives later
+        let (frames, errs) = split(
+            &[b"<13>1 - - - - - too", b" long", b"\n<13>1 - - - - - ok\n"],
+            20,
+        );
+        assert_eq!(frames, ["<13>1 - - - - - ok"]);
+        assert_eq!(errs, [Error::MessageTooLong]);
+    }
+}
@@ -0,0 +1,672 @@
+//! [GELF](https://go2docs.graylog.org/current/getting_in_log_data/gelf.html)
+//! 1.1 encoding and decoding, chunked UDP reassembly and zlib/gzip
+//! decompression, enabled by the `gelf` feature.
+//!
+//! | GELF                             | `Message`                          |
+//! |----------------------------------|------------------------------------|
+//! | `short_message`, `full_message`  | `msg`                              |
+//! | `level`                          | `severity`                         |
+//! | `host`                           | `hostname`                         |
+//! | `timestamp`                      | `timestamp`                        |
+//! | `_facility`                      | `facility`                         |
+//! | `_appname`, `_procid`, `_msgid`  | `appname`, `procid`, `msgid`       |
+//! | other `_` fields                 | structured data params             |
+//!
+//! ```
+//! let raw = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application"] An application event log entry"#;
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let payload = syslog::gelf::encode(&msg);
+//! let decoded = syslog::gelf::decode(&payload).unwrap();
+//! assert_eq!(decoded.hostname.as_deref(), Some("mymachine.example.com"));
+//!
+//! let params = &decoded.structured_data[0].params;
+//! assert!(params.contains(&("iut".to_string(), "3".to_string())));
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use serde_json::{Map, Value};
+
+use crate::{Error, Facility, Message, ProcId, Protocol, Severity, StructuredElement};
+
+/// The id of the structured element which holds the additional fields of
+/// decoded messages.
+pub const SD_ID: &str = "gelf";
+
+/// Encode `msg` as a GELF 1.1 JSON object.
+///
+/// The first line of `msg` is the `short_message`, multi-line messages are
+/// sent as `full_message` too. An empty first line and a missing hostname are
+/// sent as `-`, since GELF requires both to be non-empty. Param names
+/// are sanitized to match `^[\w.\-]*$`, and `id`, which is reserved, is sent
+/// as `_id_`. If several elements have the same param, the first one wins.
+pub fn encode<S>(msg: &Message<S>) -> Vec<u8>
+where
+    S: AsRef<str> + Ord + PartialEq + Clone,
+{
+    let text = msg.msg.as_ref();
+    let first_line = text.lines().next().unwrap_or_default();
+    let short_message = match first_line {
+        "" => "-",
+        line => line,
+    };
+
+    let mut object = Map::new();
+    object.insert("version".into(), "1.1".into());
+    object.insert(
+        "host".into(),
+        msg.hostname.as_ref().map_or("-", |h| h.as_ref()).into(),
+    );
+    object.insert("short_message".into(), short_message.into());
+    if first_line.len() != text.len() {
+        object.insert("full_message".into(), text.into());
+    }
+    if let Some(timestamp) = &msg.timestamp {
+        let micros = timestamp.timestamp_micros();
+        let seconds =
+            micros.div_euclid(1_000_000) as f64 + micros.rem_euclid(1_000_000) as f64 / 1_000_000.0;
+        object.insert("timestamp".into(), seconds.into());
+    }
+    object.insert("level".into(), (msg.severity as i32).into());
+    object.insert("_facility".into(), (msg.facility as i32).into());
+
+    if let Some(appname) = &msg.appname {
+        object.insert("_appname".into(), appname.as_ref().into());
+    }
+    match &msg.procid {
+        Some(ProcId::PID(pid)) => {
+            object.insert("_procid".into(), (*pid).into());
+        }
+        Some(ProcId::Name(name)) => {
+            object.insert("_procid".into(), name.as_ref().into());
+        }
+        None => {}
+    }
+    if let Some(msgid) = &msg.msgid {
+        object.insert("_msgid".into(), msgid.as_ref().into());
+    }
+
+    for element in &msg.structured_data {
+        for (key, value) in &element.params {
+            let mut name = String::with_capacity(key.as_ref().len() + 2);
+            name.push('_');
+            name.extend(key.as_ref().chars().map(|ch| {
+                if ch.is_alphanumeric() || ch == '_' || ch == '.' || ch == '-' {
+                    ch
+                } else {
+                    '_'
+                }
+            }));
+            if name == "_id" {
+                name.push('_');
+            }
+
+            object.entry(name).or_insert_with(|| value.as_ref().into());
+        }
+    }
+
+    serde_json::to_vec(&Value::Object(object)).expect("a Value never fails to serialize")
+}
+
+/// Decode a GELF payload, compressed or not, into a `Message`.
+///
+/// `full_message` is preferred over `short_message`. Additional fields which
+/// are not part of `Message` become the params of a single structured element
+/// with id `SD_ID`, numbers and other non-string values are kept as JSON.
+/// Without `level` the severity is `ALERT`, as the GELF spec says, and
+/// without `_facility` the facility is `USER`.
+///
+/// Additional fields are free-form, so a `_facility` which isn't a facility
+/// number, e.g. `"local0"`, is kept as a param, and a `_procid` which isn't
+/// a PID as a `ProcId::Name`.
+pub fn decode(payload: &[u8]) -> Result<Message<String>, Error> {
+    let payload = decompress(payload)?;
+    let object: Map<String, Value> =
+        serde_json::from_slice(&payload).map_err(|_| Error::InvalidGelf)?;
+
+    let mut msg = Message {
+        severity: Severity::ALERT,
+        facility: Facility::USER,
+        protocol: Protocol::Gelf,
+        timestamp: None,
+        hostname: None,
+        appname: None,
+        procid: None,
+        msgid: None,
+        structured_data: vec![],
+        msg: String::new(),
+    };
+    let mut short_message = None;
+    let mut params = vec![];
+
+    for (key, value) in object {
+        match key.as_str() {
+            "version" => {}
+            "host" => msg.hostname = Some(into_string(value)).filter(|h| h != "-"),
+            "short_message" => short_message = Some(into_string(value)),
+            "full_message" => msg.msg = into_string(value),
+            "timestamp" => msg.timestamp = Some(parse_timestamp(&value)?),
+            "level" => msg.severity = Severity::try_from(as_i32(&value)?)?,
+            "_facility" => match as_i32(&value).and_then(Facility::try_from) {
+                Ok(facility) => msg.facility = facility,
+                Err(_) => params.push(("facility".to_string(), into_string(value))),
+            },
+            "_appname" => msg.appname = Some(into_string(value)),
+            "_procid" => {
+                msg.procid = Some(match as_i32(&value) {
+                    Ok(pid) => ProcId::PID(pid),
+                    Err(_) => ProcId::Name(into_string(value)),
+                })
+            }
+            "_msgid" => msg.msgid = Some(into_string(value)),
+            _ => {
+                if let Some(name) = key.strip_prefix('_') {
+                    let name = if name == "id_" { "id" } else { name };
+                    params.push((name.to_string(), into_string(value)));
+                }
+            }
+        }
+    }
+
+    if msg.msg.is_empty() {
+        msg.msg = short_message.ok_or(Error::InvalidGelf)?;
+    }
+    if !params.is_empty() {
+        msg.structured_data.push(StructuredElement {
+            id: SD_ID.to_string(),
+            params,
+        });
+    }
+
+    Ok(msg)
+}
+
+fn into_string(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        value => value.to_string(),
+    }
+}
+
+fn as_i32(value: &Value) -> Result<i32, Error> {
+    value
+        .as_i64()
+        .and_then(|v| i32::try_from(v).ok())
+        .ok_or(Error::InvalidGelf)
+}
+
+fn parse_timestamp(value: &Value) -> Result<DateTime<FixedOffset>, Error> {
+    let seconds = value.as_f64().ok_or(Error::InvalidGelf)?;
+    DateTime::from_timestamp_micros((seconds * 1_000_000.0).round() as i64)
+        .map(|timestamp| timestamp.fixed_offset())
+        .ok_or(Error::InvalidGelf)
+}
+
+/// The maximum size of a decompressed payload, 1 MiB.
+pub const MAX_DECOMPRESSED_LEN: usize = 1024 * 1024;
+
+/// Decompress a gzip or zlib payload, anything else is returned as it is.
+///
+/// `Error::MessageTooLong` is returned if the payload decompresses to more
+/// than `MAX_DECOMPRESSED_LEN` bytes.
+pub fn decompress(payload: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    let limit = MAX_DECOMPRESSED_LEN as u64 + 1;
+    let mut out = Vec::new();
+    let result = match payload {
+        [0x1f, 0x8b, ..] => GzDecoder::new(payload).take(limit).read_to_end(&mut out),
+        // CMF is deflate with at most a 32K window, and FCHECK makes the
+        // header a multiple of 31
+        [cmf, flg, ..]
+            if cmf & 0x8f == 0x08 && (*cmf as u16 * 256 + *flg as u16).is_multiple_of(31) =>
+        {
+            ZlibDecoder::new(payload).take(limit).read_to_end(&mut out)
+        }
+        _ => return Ok(Cow::Borrowed(payload)),
+    };
+
+    result.map_err(|_| Error::InvalidGelf)?;
+    if out.len() > MAX_DECOMPRESSED_LEN {
+        return Err(Error::MessageTooLong);
+    }
+    Ok(Cow::Owned(out))
+}
+
+const CHUNK_MAGIC: [u8; 2] = [0x1e, 0x0f];
+const CHUNK_HEADER_LEN: usize = 12;
+const MAX_CHUNKS: usize = 128;
+
+/// Split `payload` into chunked GELF datagrams of at most `chunk_size` bytes,
+/// including the 12 bytes chunk header. A payload which fits into a single
+/// datagram is not chunked.
+///
+/// `Error::InvalidGelf` is returned if more than 128 chunks would be needed.
+pub fn chunks(payload: &[u8], chunk_size: usize, id: [u8; 8]) -> Result<Vec<Vec<u8>>, Error> {
+    if payload.len() <= chunk_size {
+        return Ok(vec![payload.to_vec()]);
+    }
+
+    let data_size = chunk_size
+        .checked_sub(CHUNK_HEADER_LEN)
+        .filter(|size| *size > 0)
+        .ok_or(Error::InvalidGelf)?;
+    let count = payload.len().div_ceil(data_size);
+    if count > MAX_CHUNKS {
+        return Err(Error::InvalidGelf);
+    }
+
+    Ok(payload
+        .chunks(data_size)
+        .enumerate()
+        .map(|(seq, data)| {
+            let mut chunk = Vec::with_capacity(CHUNK_HEADER_LEN + data.len());
+            chunk.extend_from_slice(&CHUNK_MAGIC);
+            chunk.extend_from_slice(&id);
+            chunk.push(seq as u8);
+            chunk.push(count as u8);
+            chunk.extend_from_slice(data);
+            chunk
+        })
+        .collect())
+}
+
+struct Pending {
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+    bytes: usize,
+    first_seen: Instant,
+}
+
+/// Reassembles chunked GELF datagrams.
+///
+/// Incomplete messages are dropped once they are older than the timeout, 5
+/// seconds by default, as the GELF spec suggests. At most 1024 messages and
+/// 16 MiB of chunks are buffered by default, the oldest messages are dropped
+/// to make room for new ones.
+pub struct Reassembler {
+    pending: HashMap<[u8; 8], Pending>,
+    timeout: Duration,
+    max_pending: usize,
+    max_bytes: usize,
+    bytes: usize,
+    /// When the oldest pending message expires.
+    next_expiry: Option<Instant>,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(5))
+    }
+}
+
+impl Reassembler {
+    pub fn new(timeout: Duration) -> Self {
+        Reassembler {
+            pending: HashMap::new(),
+            timeout,
+            max_pending: 1024,
+            max_bytes: 16 * 1024 * 1024,
+            bytes: 0,
+            next_expiry: None,
+        }
+    }
+
+    /// Set the maximum number of incomplete messages.
+    pub fn max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    /// Set the maximum number of buffered bytes, over all incomplete
+    /// messages.
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// The number of incomplete messages.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Feed a datagram received at `now`, and return the (possibly still
+    /// compressed) payload once the message is complete. Datagrams which are
+    /// not chunked are returned right away.
+    ///
+    /// `Error::MessageTooLong` is returned, and the message dropped, if its
+    /// chunks alone exceed the maximum number of buffered bytes.
+    pub fn push<'a>(
+        &mut self,
+        datagram: &'a [u8],
+        now: Instant,
+    ) -> Result<Option<Cow<'a, [u8]>>, Error> {
+        self.expire(now);
+
+        if !datagram.starts_with(&CHUNK_MAGIC) {
+            return Ok(Some(Cow::Borrowed(datagram)));
+        }
+        if datagram.len() < CHUNK_HEADER_LEN {
+            return Err(Error::InvalidGelf);
+        }
+
+        let id: [u8; 8] = datagram[2..10].try_into().unwrap();
+        let seq = datagram[10] as usize;
+        let count = datagram[11] as usize;
+        if count == 0 || count > MAX_CHUNKS || seq >= count {
+            return Err(Error::InvalidGelf);
+        }
+
+        match self.pending.get(&id) {
+            Some(pending) if pending.chunks.len() != count => {
+                self.remove(&id);
+                return Err(Error::InvalidGelf);
+            }
+            // duplicate
+            Some(pending) if pending.chunks[seq].is_some() => return Ok(None),
+            Some(_) => {}
+            None => {
+                while self.pending.len() >= self.max_pending && self.evict_oldest(None) {}
+                self.pending.insert(
+                    id,
+                    Pending {
+                        chunks: vec![None; count],
+                        received: 0,
+                        bytes: 0,
+                        first_seen: now,
+                    },
+                );
+                let expiry = now + self.timeout;
+                self.next_expiry = Some(self.next_expiry.map_or(expiry, |next| next.min(expiry)));
+            }
+        }
+
+        let data = &datagram[CHUNK_HEADER_LEN..];
+        while self.bytes + data.len() > self.max_bytes {
+            if !self.evict_oldest(Some(id)) {
+                self.remove(&id);
+                return Err(Error::MessageTooLong);
+            }
+        }
+
+        let pending = self.pending.get_mut(&id).unwrap();
+        pending.chunks[seq] = Some(data.to_vec());
+        pending.received += 1;
+        pending.bytes += data.len();
+        self.bytes += data.len();
+        if pending.received < count {
+            return Ok(None);
+        }
+
+        let pending = self.remove(&id).unwrap();
+        Ok(Some(Cow::Owned(
+            pending.chunks.into_iter().flatten().flatten().collect(),
+        )))
+    }
+
+    /// Drop the expired messages, only once the oldest one has expired.
+    fn expire(&mut self, now: Instant) {
+        if self.next_expiry.is_none_or(|next| now < next) {
+            return;
+        }
+
+        let timeout = self.timeout;
+        let bytes = &mut self.bytes;
+        self.pending.retain(|_, pending| {
+            let keep = now.duration_since(pending.first_seen) < timeout;
+            if !keep {
+                *bytes -= pending.bytes;
+            }
+            keep
+        });
+        self.next_expiry = self
+            .pending
+            .values()
+            .map(|pending| pending.first_seen + timeout)
+            .min();
+    }
+
+    /// Drop the oldest message other than `keep`, returns false if there is
+    /// none.
+    fn evict_oldest(&mut self, keep: Option<[u8; 8]>) -> bool {
+        let oldest = self
+            .pending
+            .iter()
+            .filter(|(id, _)| Some(**id) != keep)
+            .min_by_key(|(_, pending)| pending.first_seen)
+            .map(|(id, _)| *id);
+        match oldest {
+            Some(id) => {
+                self.remove(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove(&mut self, id: &[u8; 8]) -> Option<Pending> {
+        let pending = self.pending.remove(id)?;
+        self.bytes -= pending.bytes;
+        Some(pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let raw = b"<165>1 2003-10-11T22:14:15.003456Z mymachine.example.com evntslog 8710 ID47 [exampleSDID@32473 iut=\"3\" event@Source=\"Application\" id=\"1011\"] first line\nsecond line";
+        let msg = crate::rfc5424::parse_message(raw).unwrap();
+
+        let payload = encode(&msg);
+        let object: Map<String, Value> = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(object["version"], "1.1");
+        assert_eq!(object["short_message"], "first line");
+        assert_eq!(object["full_message"], "first line\nsecond line");
+        assert_eq!(object["level"], 5);
+        assert_eq!(object["_facility"], 20);
+        assert_eq!(object["_procid"], 8710);
+        assert_eq!(object["_event_Source"], "Application");
+        assert_eq!(object["_id_"], "1011");
+
+        let decoded = decode(&payload).unwrap();
+        assert_eq!(decoded.severity, msg.severity);
+        assert_eq!(decoded.facility, msg.facility);
+        assert_eq!(decoded.protocol, Protocol::Gelf);
+        assert_eq!(decoded.timestamp, msg.timestamp);
+        assert_eq!(decoded.hostname.as_deref(), msg.hostname);
+        assert_eq!(decoded.appname.as_deref(), msg.appname);
+        assert_eq!(decoded.procid, Some(ProcId::PID(8710)));
+        assert_eq!(decoded.msgid.as_deref(), msg.msgid);
+        assert_eq!(decoded.msg, msg.msg);
+        assert_eq!(
+            decoded.structured_data,
+            [StructuredElement {
+                id: SD_ID.to_string(),
+                params: vec![
+                    ("iut".to_string(), "3".to_string()),
+                    ("event_Source".to_string(), "Application".to_string()),
+                    ("id".to_string(), "1011".to_string()),
+                ],
+            }]
+        );
+    }
+
+    #[test]
+    fn empty_message() {
+        let msg = crate::rfc5424::parse_message(b"<14>1 - - - - - -").unwrap();
+        let object: Map<String, Value> = serde_json::from_slice(&encode(&msg)).unwrap();
+        assert_eq!(object["short_message"], "-");
+        assert!(!object.contains_key("full_message"));
+    }
+
+    #[test]
+    fn decode_graylog_example() {
+        let payload = br#"{
+            "version": "1.1",
+            "host": "example.org",
+            "short_message": "A short message that helps you identify what is going on",
+            "timestamp": 1385053862.3072,
+            "level": 1,
+            "_user_id": 9001,
+            "_some_info": "foo"
+        }"#;
+
+        let msg = decode(payload).unwrap();
+        assert_eq!(msg.severity, Severity::ALERT);
+        assert_eq!(msg.facility, Facility::USER);
+        assert_eq!(
+            msg.msg,
+            "A short message that helps you identify what is going on"
+        );
+        assert_eq!(
+            msg.timestamp.unwrap().to_rfc3339(),
+            "2013-11-21T17:11:02.307200+00:00"
+        );
+        assert_eq!(msg.structured_data[0].params.len(), 2);
+
+        // odd additional fields don't drop the message
+        let msg =
+            decode(br#"{"short_message": "a", "_facility": "local0", "_procid": 4294967296}"#)
+                .unwrap();
+        assert_eq!(msg.facility, Facility::USER);
+        assert_eq!(msg.procid, Some(ProcId::Name("4294967296".to_string())));
+        assert_eq!(
+            msg.structured_data[0].params,
+            [("facility".to_string(), "local0".to_string())]
+        );
+
+        for payload in [
+            &b""[..],
+            b"[]",
+            br#"{"host": "example.org"}"#,
+            br#"{"short_message": "a", "level": 8}"#,
+            br#"{"short_message": "a", "timestamp": "now"}"#,
+        ] {
+            assert!(decode(payload).is_err(), "{payload:?}");
+        }
+    }
+
+    #[test]
+    fn compression() {
+        let payload = br#"{"version":"1.1","host":"a","short_message":"compressed"}"#;
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(payload).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(payload).unwrap();
+        let zlib = zlib.finish().unwrap();
+
+        for input in [&payload[..], &gzip, &zlib] {
+            assert_eq!(decompress(input).unwrap(), &payload[..]);
+            assert_eq!(decode(input).unwrap().msg, "compressed");
+        }
+
+        assert_eq!(decompress(&gzip[..10]), Err(Error::InvalidGelf));
+
+        // a small payload may decompress to a lot of data
+        let mut bomb = ZlibEncoder::new(Vec::new(), Compression::best());
+        bomb.write_all(&vec![b' '; MAX_DECOMPRESSED_LEN + 1])
+            .unwrap();
+        let bomb = bomb.finish().unwrap();
+        assert!(bomb.len() < 8192);
+        assert_eq!(decompress(&bomb), Err(Error::MessageTooLong));
+    }
+
+    #[test]
+    fn reassemble() {
+        let payload = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut datagrams = chunks(&payload, 112, *b"abcdefgh").unwrap();
+        assert_eq!(datagrams.len(), 10);
+        datagrams.reverse();
+
+        let now = Instant::now();
+        let mut reassembler = Reassembler::default();
+        for datagram in &datagrams[..9] {
+            assert_eq!(reassembler.push(datagram, now), Ok(None));
+            // duplicates are ignored
+            assert_eq!(reassembler.push(datagram, now), Ok(None));
+        }
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(
+            reassembler.push(&datagrams[9], now).unwrap().unwrap(),
+            payload
+        );
+        assert_eq!(reassembler.pending(), 0);
+
+        // expired
+        assert_eq!(reassembler.push(&datagrams[0], now), Ok(None));
+        let later = now + Duration::from_secs(6);
+        assert_eq!(reassembler.push(&datagrams[1], later), Ok(None));
+        assert_eq!(reassembler.pending(), 1);
+
+        assert_eq!(chunks(&payload, 112, [0; 8]).unwrap().len(), 10);
+        assert_eq!(chunks(&payload, 2000, [0; 8]).unwrap(), [&payload[..]]);
+        assert_eq!(chunks(&payload, 12, [0; 8]), Err(Error::InvalidGelf));
+        assert_eq!(chunks(&payload, 19, [0; 8]), Err(Error::InvalidGelf));
+
+        // the oldest messages are evicted
+        let mut reassembler = Reassembler::default().max_pending(2);
+        for (i, id) in [*b"aaaaaaaa", *b"bbbbbbbb", *b"cccccccc"]
+            .iter()
+            .enumerate()
+        {
+            let datagram = &chunks(&payload, 112, *id).unwrap()[0];
+            let at = now + Duration::from_millis(i as u64);
+            assert_eq!(reassembler.push(datagram, at), Ok(None));
+        }
+        assert_eq!(reassembler.pending(), 2);
+        let datagrams = chunks(&payload, 112, *b"cccccccc").unwrap();
+        for datagram in &datagrams[1..9] {
+            assert_eq!(reassembler.push(datagram, now), Ok(None));
+        }
+        assert_eq!(
+            reassembler.push(&datagrams[9], now).unwrap().unwrap(),
+            payload
+        );
+        let datagrams = chunks(&payload, 112, *b"aaaaaaaa").unwrap();
+        for datagram in &datagrams[1..] {
+            assert_eq!(reassembler.push(datagram, now), Ok(None));
+        }
+
+        let mut reassembler = Reassembler::default().max_bytes(250);
+        let first = chunks(&payload, 112, *b"aaaaaaaa").unwrap();
+        let second = chunks(&payload, 112, *b"bbbbbbbb").unwrap();
+        for datagram in [&first[0], &first[1], &second[0]] {
+            assert_eq!(reassembler.push(datagram, now), Ok(None));
+        }
+        assert_eq!(reassembler.pending(), 1);
+        assert_eq!(reassembler.push(&second[1], now), Ok(None));
+        assert_eq!(
+            reassembler.push(&second[2], now),
+            Err(Error::MessageTooLong)
+        );
+        assert_eq!(reassembler.pending(), 0);
+
+        let mut reassembler = Reassembler::default();
+        for datagram in [
+            &[0x1e, 0x0f, 1, 2][..],
+            &[0x1e, 0x0f, 0, 0, 0, 0, 0, 0, 0, 0, 2, 2, 0],
+            &[0x1e, 0x0f, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ] {
+            assert_eq!(
+                reassembler.push(datagram, now),
+                Err(Error::InvalidGelf),
+                "{datagram:?}"
+            );
+        }
+    }
+}
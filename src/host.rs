@@ -0,0 +1,141 @@
+//! Classification of the HOSTNAME field.
+
+use core::net::IpAddr;
+
+use crate::Error;
+
+/// The HOSTNAME field of a message, see `Message::host`.
+///
+/// RFC 5424 recommends, in order of preference, a FQDN, a static IP address,
+/// a hostname or a dynamic IP address.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Host<'a> {
+    Ip(IpAddr),
+    /// A name with more than one label, e.g. `mymachine.example.com`, the
+    /// trailing dot of an absolute name is kept.
+    Fqdn(&'a str),
+    /// A single label, e.g. `mymachine`
+    Simple(&'a str),
+}
+
+impl<'a> Host<'a> {
+    /// Classify `s`, returns `Error::InvalidHostname` if it is neither an IP
+    /// address nor a valid domain name.
+    ///
+    /// Labels follow RFC 1034, as relaxed by RFC 1123: 1 to 63 letters, digits
+    /// or hyphens, not starting or ending with a hyphen. The whole name is at
+    /// most 253 bytes, not counting the trailing dot. The last label can't be
+    /// all digits, so invalid IPv4 addresses like `256.1.1.1` aren't names
+    /// (RFC 1123 section 2.1).
+    pub fn parse(s: &'a str) -> Result<Self, Error> {
+        if let Ok(addr) = s.parse::<IpAddr>() {
+            return Ok(Host::Ip(addr));
+        }
+
+        let name = s.strip_suffix('.').unwrap_or(s);
+        if name.is_empty() || name.len() > 253 {
+            return Err(Error::InvalidHostname);
+        }
+
+        let mut labels = 0;
+        let mut numeric = false;
+        for label in name.split('.') {
+            if !is_valid_label(label.as_bytes()) {
+                return Err(Error::InvalidHostname);
+            }
+
+            labels += 1;
+            numeric = label.bytes().all(|ch| ch.is_ascii_digit());
+        }
+        if numeric {
+            return Err(Error::InvalidHostname);
+        }
+
+        if labels == 1 && name.len() == s.len() {
+            Ok(Host::Simple(s))
+        } else {
+            Ok(Host::Fqdn(s))
+        }
+    }
+
+    pub fn is_ip(&self) -> bool {
+        matches!(self, Host::Ip(_))
+    }
+
+    pub fn is_fqdn(&self) -> bool {
+        matches!(self, Host::Fqdn(_))
+    }
+}
+
+fn is_valid_label(label: &[u8]) -> bool {
+    match (label.first(), label.last()) {
+        (Some(first), Some(last)) => {
+            label.len() <= 63
+                && first.is_ascii_alphanumeric()
+                && last.is_ascii_alphanumeric()
+                && label
+                    .iter()
+                    .all(|ch| ch.is_ascii_alphanumeric() || *ch == b'-')
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn classify() {
+        for (input, want) in [
+            ("42.52.1.1", Host::Ip(Ipv4Addr::new(42, 52, 1, 1).into())),
+            ("::1", Host::Ip(Ipv6Addr::LOCALHOST.into())),
+            (
+                "::FFFF:129.144.52.38",
+                Host::Ip(Ipv4Addr::new(129, 144, 52, 38).to_ipv6_mapped().into()),
+            ),
+            ("mymachine.example.com", Host::Fqdn("mymachine.example.com")),
+            ("example.com.", Host::Fqdn("example.com.")),
+            ("localhost.", Host::Fqdn("localhost.")),
+            ("mymachine", Host::Simple("mymachine")),
+            ("74794bfb6795", Host::Simple("74794bfb6795")),
+            ("my-machine-01", Host::Simple("my-machine-01")),
+            ("3com.example", Host::Fqdn("3com.example")),
+            ("host.123a", Host::Fqdn("host.123a")),
+        ] {
+            assert_eq!(Host::parse(input), Ok(want), "{input}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        let long_label = "a".repeat(64);
+        let long_name = ["abcdefghi"; 26].join(".");
+        assert_eq!(long_name.len(), 259);
+
+        for input in [
+            "",
+            ".",
+            "my_machine",
+            "-machine",
+            "machine-",
+            "example..com",
+            ".example.com",
+            "example.com..",
+            "exämple.com",
+            "1.2.3.4:514",
+            "256.1.1.1",
+            "1.2.3",
+            "12345",
+            long_label.as_str(),
+            long_name.as_str(),
+        ] {
+            assert_eq!(Host::parse(input), Err(Error::InvalidHostname), "{input}");
+        }
+
+        let max_label = "a".repeat(63);
+        assert!(Host::parse(&max_label).is_ok());
+    }
+}
@@ -0,0 +1,410 @@
+//! Bridge between `Message` and the systemd journal, and a parser and writer
+//! for the [journal export format](https://systemd.io/JOURNAL_EXPORT_FORMATS/)
+//! of `journalctl -o export`.
+//!
+//! | journal field                     | `Message`         |
+//! |-----------------------------------|-------------------|
+//! | `PRIORITY`                        | `severity`        |
+//! | `SYSLOG_FACILITY`                 | `facility`        |
+//! | `SYSLOG_IDENTIFIER`               | `appname`         |
+//! | `_PID`, `SYSLOG_PID`              | `procid`          |
+//! | `_HOSTNAME`                       | `hostname`        |
+//! | `MESSAGE`                         | `msg`             |
+//! | `__REALTIME_TIMESTAMP`, `SYSLOG_TIMESTAMP` | `timestamp` |
+//!
+//! ```
+//! use syslog::journal::{write_message, Entries};
+//!
+//! let raw = b"<30>1 2023-04-07T12:52:00.123Z mymachine sshd 8449 - - Accepted publickey";
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let mut export = Vec::new();
+//! write_message(&msg, &mut export);
+//!
+//! let entry = Entries::new(&export).next().unwrap().unwrap();
+//! assert_eq!(entry.get("SYSLOG_IDENTIFIER"), Some(&b"sshd"[..]));
+//!
+//! let replayed = entry.to_message().unwrap();
+//! assert_eq!(replayed.timestamp, msg.timestamp);
+//! assert_eq!(replayed.msg, "Accepted publickey");
+//! ```
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use chrono::{DateTime, Datelike, FixedOffset, Timelike, Utc};
+
+use crate::rfc3164::{parse_timestamp, Resolver, MONTHS};
+use crate::{Error, Facility, Message, ProcId, Protocol, Severity};
+
+/// A journal entry, i.e. a list of fields. Field values are arbitrary bytes,
+/// and borrow from the input.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Entry<'a> {
+    pub fields: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the value of the first field named `key`.
+    pub fn get(&self, key: &str) -> Option<&'a [u8]> {
+        self.fields.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+    }
+
+    /// Same as `get`, but the value must be valid UTF-8.
+    pub fn get_str(&self, key: &str) -> Result<Option<&'a str>, Error> {
+        self.get(key)
+            .map(|value| core::str::from_utf8(value).map_err(|_| Error::InvalidJournal))
+            .transpose()
+    }
+
+    /// Convert the entry into a `Message`, see the module docs for the
+    /// mapping.
+    ///
+    /// Without `PRIORITY` or `SYSLOG_FACILITY`, the severity is `INFO` and the
+    /// facility is `USER`, which is what journald assumes as well.
+    ///
+    /// `__REALTIME_TIMESTAMP` is the time the entry was received, in UTC. If
+    /// `SYSLOG_TIMESTAMP` is there as well, the sender's UTC offset is inferred
+    /// from the difference between the two. `SYSLOG_TIMESTAMP` alone has no
+    /// year, so it is ignored.
+    pub fn to_message(&self) -> Result<Message<&'a str>, Error> {
+        let severity = match self.get_str("PRIORITY")? {
+            Some(value) => Severity::try_from(parse_int(value)?)?,
+            None => Severity::INFO,
+        };
+        let facility = match self.get_str("SYSLOG_FACILITY")? {
+            Some(value) => Facility::try_from(parse_int(value)?)?,
+            None => Facility::USER,
+        };
+
+        let procid = match self.get_str("_PID")? {
+            Some(pid) => Some(pid),
+            None => self.get_str("SYSLOG_PID")?,
+        };
+
+        Ok(Message {
+            severity,
+            facility,
+            protocol: Protocol::Journal,
+            timestamp: self.timestamp()?,
+            hostname: self.get_str("_HOSTNAME")?,
+            appname: self.get_str("SYSLOG_IDENTIFIER")?,
+            procid: procid.map(ProcId::from),
+            msgid: None,
+            structured_data: Vec::new(),
+            msg: self.get_str("MESSAGE")?.unwrap_or_default(),
+        })
+    }
+
+    fn timestamp(&self) -> Result<Option<DateTime<FixedOffset>>, Error> {
+        let Some(realtime) = self.get_str("__REALTIME_TIMESTAMP")? else {
+            return Ok(None);
+        };
+        let realtime = realtime
+            .parse::<i64>()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or(Error::InvalidJournal)?;
+
+        let Some(syslog_timestamp) = self.get("SYSLOG_TIMESTAMP") else {
+            return Ok(Some(realtime.fixed_offset()));
+        };
+
+        // resolve the local time as if it was UTC, the difference to the
+        // realtime timestamp is the sender's offset, rounded to 15 minutes.
+        let ts = parse_timestamp(syslog_timestamp, &mut 0)?;
+        let local = Resolver::new(Utc).resolve(&ts, realtime)?;
+        let diff = local.timestamp() - realtime.timestamp();
+        let offset = (diff + 450).div_euclid(900) * 900;
+
+        match i32::try_from(offset).ok().and_then(FixedOffset::east_opt) {
+            Some(offset) => Ok(Some(realtime.with_timezone(&offset))),
+            None => Ok(Some(realtime.fixed_offset())),
+        }
+    }
+
+    /// Append the entry to `out` in the export format.
+    pub fn write_to(&self, out: &mut Vec<u8>) {
+        for (key, value) in &self.fields {
+            write_field(out, key, value);
+        }
+
+        out.push(b'\n');
+    }
+}
+
+fn parse_int(value: &str) -> Result<i32, Error> {
+    value.trim().parse().map_err(|_| Error::InvalidJournal)
+}
+
+/// An iterator over the entries of an export format stream, it stops after
+/// the first error.
+#[derive(Clone, Debug)]
+pub struct Entries<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Entries<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Entries { buf, pos: 0 }
+    }
+
+    fn parse_entry(&mut self) -> Result<Entry<'a>, Error> {
+        let buf = self.buf;
+        let mut entry = Entry::default();
+
+        while self.pos < buf.len() {
+            if buf[self.pos] == b'\n' {
+                self.pos += 1;
+                break;
+            }
+
+            let end = buf[self.pos..]
+                .iter()
+                .position(|ch| *ch == b'\n' || *ch == b'=')
+                .map(|pos| self.pos + pos)
+                .ok_or(Error::InvalidJournal)?;
+            let key =
+                core::str::from_utf8(&buf[self.pos..end]).map_err(|_| Error::InvalidJournal)?;
+            if key.is_empty() {
+                return Err(Error::InvalidJournal);
+            }
+
+            let value = if buf[end] == b'=' {
+                let value_end = buf[end..]
+                    .iter()
+                    .position(|ch| *ch == b'\n')
+                    .map_or(buf.len(), |pos| end + pos);
+                self.pos = value_end + 1;
+                &buf[end + 1..value_end]
+            } else {
+                // binary field, a little-endian u64 length and the raw value
+                let start = end + 1 + 8;
+                let len = buf
+                    .get(end + 1..start)
+                    .map(|len| u64::from_le_bytes(len.try_into().unwrap()))
+                    .and_then(|len| usize::try_from(len).ok())
+                    .ok_or(Error::InvalidJournal)?;
+                let value_end = start.checked_add(len).ok_or(Error::InvalidJournal)?;
+                if buf.get(value_end) != Some(&b'\n') {
+                    return Err(Error::InvalidJournal);
+                }
+                self.pos = value_end + 1;
+                &buf[start..value_end]
+            };
+
+            entry.fields.push((key, value));
+        }
+
+        Ok(entry)
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // extra empty lines between entries are tolerated
+        while self.buf.get(self.pos) == Some(&b'\n') {
+            self.pos += 1;
+        }
+        if self.pos >= self.buf.len() {
+            return None;
+        }
+
+        let result = self.parse_entry();
+        if result.is_err() {
+            self.pos = self.buf.len();
+        }
+
+        Some(result)
+    }
+}
+
+/// Append a single field to `out`, values with control characters other than
+/// tab, or which are not UTF-8, are written in the binary-safe encoding.
+pub fn write_field(out: &mut Vec<u8>, key: &str, value: &[u8]) {
+    out.extend_from_slice(key.as_bytes());
+
+    let binary = core::str::from_utf8(value).is_err()
+        || value.iter().any(|ch| ch.is_ascii_control() && *ch != b'\t');
+    if binary {
+        out.push(b'\n');
+        out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        out.push(b'=');
+    }
+
+    out.extend_from_slice(value);
+    out.push(b'\n');
+}
+
+/// Append `msg` to `out` as an export format entry, see the module docs for
+/// the mapping. Structured data is not written.
+///
+/// The timestamp is written as `__REALTIME_TIMESTAMP`, and as the local
+/// `SYSLOG_TIMESTAMP`, so `Entry::to_message` restores its offset too.
+pub fn write_message<S, D>(msg: &Message<S, D>, out: &mut Vec<u8>)
+where
+    S: AsRef<str> + Ord + PartialEq + Clone,
+{
+    if let Some(timestamp) = &msg.timestamp {
+        let realtime = timestamp.timestamp_micros().to_string();
+        write_field(out, "__REALTIME_TIMESTAMP", realtime.as_bytes());
+
+        let mut buf = *b"Jan  1 00:00:00";
+        buf[..3].copy_from_slice(MONTHS[timestamp.month0() as usize]);
+        for (pos, value) in [
+            (4, timestamp.day()),
+            (7, timestamp.hour()),
+            (10, timestamp.minute()),
+            // chrono keeps leap seconds as nanos beyond 1_000_000_000
+            (
+                13,
+                timestamp.second() + timestamp.nanosecond() / 1_000_000_000,
+            ),
+        ] {
+            buf[pos] = b'0' + (value / 10) as u8;
+            buf[pos + 1] = b'0' + (value % 10) as u8;
+        }
+        if buf[4] == b'0' {
+            buf[4] = b' ';
+        }
+        write_field(out, "SYSLOG_TIMESTAMP", &buf);
+    }
+
+    write_field(
+        out,
+        "PRIORITY",
+        (msg.severity as i32).to_string().as_bytes(),
+    );
+    write_field(
+        out,
+        "SYSLOG_FACILITY",
+        (msg.facility as i32).to_string().as_bytes(),
+    );
+    if let Some(hostname) = &msg.hostname {
+        write_field(out, "_HOSTNAME", hostname.as_ref().as_bytes());
+    }
+    if let Some(appname) = &msg.appname {
+        write_field(out, "SYSLOG_IDENTIFIER", appname.as_ref().as_bytes());
+    }
+    match &msg.procid {
+        Some(ProcId::PID(pid)) => write_field(out, "_PID", pid.to_string().as_bytes()),
+        Some(ProcId::Name(name)) => write_field(out, "SYSLOG_PID", name.as_ref().as_bytes()),
+        None => {}
+    }
+    write_field(out, "MESSAGE", msg.msg.as_ref().as_bytes());
+
+    out.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // hand-written in the format of `journalctl -o export`, the second entry
+    // has only a few fields and a binary MESSAGE
+    const EXPORT: &[u8] = b"__CURSOR=s=739ad463348b4ceca5a9e69c95a3c93f;i=4ece7;b=6c7c6013a8764b09a3c6d2cd8c9fc8ff;m=264a21f0;t=5f8cb9e3f6b6c;x=b31f5a48e6ee1637\n\
+__REALTIME_TIMESTAMP=1680871920654316\n\
+__MONOTONIC_TIMESTAMP=642392560\n\
+_BOOT_ID=6c7c6013a8764b09a3c6d2cd8c9fc8ff\n\
+PRIORITY=6\n\
+SYSLOG_FACILITY=4\n\
+SYSLOG_IDENTIFIER=sshd\n\
+_PID=8449\n\
+_HOSTNAME=mymachine\n\
+MESSAGE=Accepted publickey for root from 10.0.0.1 port 52318 ssh2\n\
+\n\
+__REALTIME_TIMESTAMP=1680871921000000\n\
+PRIORITY=3\n\
+SYSLOG_IDENTIFIER=app\n\
+MESSAGE\n\x0c\x00\x00\x00\x00\x00\x00\x00first\nsecond\n\
+\n";
+
+    #[test]
+    fn parse() {
+        let entries = Entries::new(EXPORT).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].fields.len(), 10);
+        assert_eq!(entries[1].get("MESSAGE"), Some(&b"first\nsecond"[..]));
+
+        let msg = entries[0].to_message().unwrap();
+        assert_eq!(msg.severity, Severity::INFO);
+        assert_eq!(msg.facility, Facility::AUTH);
+        assert_eq!(msg.protocol, Protocol::Journal);
+        assert_eq!(msg.appname, Some("sshd"));
+        assert_eq!(msg.procid, Some(ProcId::PID(8449)));
+        assert_eq!(msg.hostname, Some("mymachine"));
+        assert_eq!(
+            msg.msg,
+            "Accepted publickey for root from 10.0.0.1 port 52318 ssh2"
+        );
+        assert_eq!(
+            msg.timestamp.unwrap().to_rfc3339(),
+            "2023-04-07T12:52:00.654316+00:00"
+        );
+
+        let msg = entries[1].to_message().unwrap();
+        assert_eq!(msg.severity, Severity::ERR);
+        assert_eq!(msg.facility, Facility::USER);
+        assert_eq!(msg.procid, None);
+        assert_eq!(msg.msg, "first\nsecond");
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut out = Vec::new();
+        for entry in Entries::new(EXPORT) {
+            entry.unwrap().write_to(&mut out);
+        }
+        assert_eq!(out, EXPORT);
+
+        for raw in [
+            &b"<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts."[..],
+            b"<34>1 2003-10-11T22:14:15.003+05:45 mymachine.example.com su worker - - multi\nline",
+            b"<34>1 - - - - - - no timestamp",
+        ] {
+            let msg = crate::rfc5424::parse_message(raw).unwrap();
+            let mut out = Vec::new();
+            write_message(&msg, &mut out);
+
+            let entry = Entries::new(&out).next().unwrap().unwrap();
+            let got = entry.to_message().unwrap();
+            assert_eq!(got.protocol, Protocol::Journal);
+            assert_eq!(
+                Message {
+                    protocol: msg.protocol.clone(),
+                    msgid: msg.msgid,
+                    ..got
+                },
+                msg
+            );
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            &b"MESSAGE"[..],
+            b"MESSAGE\n\x05\x00\x00\x00\x00\x00\x00\x00abc\n",
+            b"MESSAGE\n\x03\x00\x00\x00\x00\x00\x00\x00abcd\n",
+            b"MESSAGE\n\xff\xff\xff\xff\xff\xff\xff\xffabc\n",
+            b"=value\n",
+            b"\xff=value\n",
+        ] {
+            assert_eq!(
+                Entries::new(input).next(),
+                Some(Err(Error::InvalidJournal)),
+                "{input:?}"
+            );
+        }
+
+        let mut entries = Entries::new(b"PRIORITY=x\n\nMESSAGE\nbroken");
+        let entry = entries.next().unwrap().unwrap();
+        assert_eq!(entry.to_message(), Err(Error::InvalidJournal));
+        assert!(entries.next().unwrap().is_err());
+        assert_eq!(entries.next(), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Structured JSON bodies in MSG, enabled by the `serde_json` feature.
+//!
+//! Applications following [CEE](https://cee.mitre.org/)/Lumberjack prefix
+//! the JSON object with the `@cee:` cookie, others just log a JSON object.
+//!
+//! ```
+//! let raw = br#"<14>1 2023-04-07T12:52:00Z web01 app 8449 - - @cee: {"msg":"request done","status":200}"#;
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let body = msg.json().unwrap();
+//! assert!(body.cee);
+//! assert_eq!(body.fields["status"], 200);
+//!
+//! // anything else is plain text
+//! let raw = b"<14>1 2023-04-07T12:52:00Z web01 app 8449 - - {not json";
+//! assert!(syslog::rfc5424::parse_message(raw).unwrap().json().is_none());
+//! ```
+
+use alloc::string::String;
+
+use serde_json::{Map, Value};
+
+const CEE_COOKIE: &str = "@cee:";
+
+/// A JSON object found in MSG.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonBody {
+    /// Whether the object was prefixed with the `@cee:` cookie
+    pub cee: bool,
+    pub fields: Map<String, Value>,
+}
+
+/// Parse `msg` as a JSON object, with or without the `@cee:` cookie.
+///
+/// Leading whitespace and the UTF-8 BOM are skipped. `None` is returned if
+/// `msg` doesn't look like JSON, or is not a valid JSON object, so callers can
+/// fall back to treating it as plain text.
+pub fn extract(msg: &str) -> Option<JsonBody> {
+    let msg = msg.strip_prefix('\u{feff}').unwrap_or(msg).trim_start();
+
+    let (cee, body) = match msg.strip_prefix(CEE_COOKIE) {
+        Some(body) => (true, body.trim_start()),
+        None => (false, msg),
+    };
+
+    if !body.starts_with('{') {
+        return None;
+    }
+
+    serde_json::from_str(body)
+        .ok()
+        .map(|fields| JsonBody { cee, fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bodies() {
+        for (input, cee) in [
+            (r#"@cee: {"msg":"hello","pid":42}"#, true),
+            (r#"@cee:{"msg":"hello","pid":42}"#, true),
+            (r#"  {"msg":"hello","pid":42}  "#, false),
+            ("\u{feff}{\"msg\":\"hello\",\"pid\":42}", false),
+        ] {
+            let body = extract(input).unwrap();
+            assert_eq!(body.cee, cee, "{input}");
+            assert_eq!(body.fields["msg"], "hello", "{input}");
+            assert_eq!(body.fields["pid"], 42, "{input}");
+        }
+    }
+
+    #[test]
+    fn plain_text() {
+        for input in [
+            "",
+            "hello world",
+            "@cee:",
+            "@cee: not json",
+            r#"{"msg":"truncated"#,
+            r#"{"msg":"hello"} trailing"#,
+            "[1, 2, 3]",
+            r#"@cee: [{"msg":"hello"}]"#,
+            r#"prefix {"msg":"hello"}"#,
+        ] {
+            assert_eq!(extract(input), None, "{input}");
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Parser for the records of Linux's [`/dev/kmsg`](https://www.kernel.org/doc/Documentation/ABI/testing/dev-kmsg).
+//!
+//! Every `read()` of `/dev/kmsg` returns one record like
+//!
+//! ```text
+//! 6,339,5140900,-;NET: Registered protocol family 10
+//!  SUBSYSTEM=net
+//!  DEVICE=+net:eth0
+//! ```
+//!
+//! i.e. `PRI,SEQ,TS_USEC,FLAGS;MESSAGE`, followed by optional dictionary
+//! lines, which start with a space.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use chrono::{DateTime, FixedOffset, TimeDelta};
+
+use crate::{Error, Facility, Message, Protocol, Severity, StructuredElement};
+
+/// The id of the structured element holding the dictionary lines.
+pub const SD_ID: &str = "kmsg";
+
+/// A `/dev/kmsg` record
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record<'a> {
+    /// Sequence number, a gap means that records were overwritten before
+    /// they were read.
+    pub seq: u64,
+    /// Microseconds since boot, from the monotonic clock.
+    pub monotonic_usec: u64,
+    /// `-` for a complete record, `c` for the first fragment of a
+    /// continued line and `+` for the following ones (older kernels).
+    pub flags: &'a str,
+    /// `appname` is always `kernel`, and `timestamp` is `boot_time` plus
+    /// `monotonic_usec`. The dictionary lines, if any, are the params of a
+    /// single structured element with id `SD_ID`.
+    pub message: Message<&'a str>,
+}
+
+/// Parse a single record, the trailing newline is optional.
+///
+/// `boot_time` is the wall clock time when the system booted, e.g. the
+/// current time minus the `CLOCK_MONOTONIC`/`CLOCK_BOOTTIME` uptime. Fields
+/// after FLAGS in the prefix, which newer kernels may add, are ignored.
+/// Non-printable characters are escaped as `\xNN` by the kernel, and are left
+/// as they are.
+pub fn parse_record(buf: &[u8], boot_time: DateTime<FixedOffset>) -> Result<Record<'_>, Error> {
+    let buf = core::str::from_utf8(buf).map_err(|_| Error::InvalidKmsg)?;
+    let buf = buf.strip_suffix('\n').unwrap_or(buf);
+
+    let (prefix, rest) = buf.split_once(';').ok_or(Error::InvalidKmsg)?;
+    let mut fields = prefix.split(',');
+    let mut next = || fields.next().ok_or(Error::InvalidKmsg);
+
+    let prival = next()?.parse::<i32>().map_err(|_| Error::InvalidKmsg)?;
+    let seq = next()?.parse::<u64>().map_err(|_| Error::InvalidKmsg)?;
+    let monotonic_usec = next()?.parse::<u64>().map_err(|_| Error::InvalidKmsg)?;
+    let flags = next()?;
+
+    let severity = Severity::try_from(prival & 0x7)?;
+    let facility = Facility::try_from(prival >> 3)?;
+
+    let mut lines = rest.split('\n');
+    let msg = lines.next().unwrap_or_default();
+
+    let mut params = Vec::new();
+    for line in lines {
+        let line = line.strip_prefix(' ').ok_or(Error::InvalidKmsg)?;
+        let (key, value) = line.split_once('=').ok_or(Error::InvalidKmsg)?;
+        params.push((key, value));
+    }
+
+    let structured_data = if params.is_empty() {
+        vec![]
+    } else {
+        vec![StructuredElement { id: SD_ID, params }]
+    };
+
+    let timestamp = i64::try_from(monotonic_usec)
+        .ok()
+        .and_then(|usec| boot_time.checked_add_signed(TimeDelta::microseconds(usec)))
+        .ok_or(Error::InvalidKmsg)?;
+
+    Ok(Record {
+        seq,
+        monotonic_usec,
+        flags,
+        message: Message {
+            severity,
+            facility,
+            protocol: Protocol::Kmsg,
+            timestamp: Some(timestamp),
+            hostname: None,
+            appname: Some("kernel"),
+            procid: None,
+            msgid: None,
+            structured_data,
+            msg,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn boot_time() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(0)
+            .unwrap()
+            .with_ymd_and_hms(2024, 3, 1, 8, 0, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn samples() {
+        let record = parse_record(
+            b"6,339,5140900,-;NET: Registered protocol family 10\n SUBSYSTEM=net\n DEVICE=+net:eth0\n",
+            boot_time(),
+        )
+        .unwrap();
+
+        assert_eq!(record.seq, 339);
+        assert_eq!(record.monotonic_usec, 5140900);
+        assert_eq!(record.flags, "-");
+
+        let msg = record.message;
+        assert_eq!(msg.facility, Facility::KERN);
+        assert_eq!(msg.severity, Severity::INFO);
+        assert_eq!(msg.protocol, Protocol::Kmsg);
+        assert_eq!(msg.appname, Some("kernel"));
+        assert_eq!(msg.msg, "NET: Registered protocol family 10");
+        assert_eq!(
+            msg.timestamp.unwrap().to_rfc3339(),
+            "2024-03-01T08:00:05.140900+00:00"
+        );
+        assert_eq!(
+            msg.structured_data,
+            [StructuredElement {
+                id: SD_ID,
+                params: vec![("SUBSYSTEM", "net"), ("DEVICE", "+net:eth0")],
+            }]
+        );
+
+        // written by userspace, e.g. systemd, and with an extra prefix field
+        let record = parse_record(
+            b"30,1019,6351233,-,caller=T1;systemd[1]: Started Journal Service.",
+            boot_time(),
+        )
+        .unwrap();
+        assert_eq!(record.message.facility, Facility::DAEMON);
+        assert_eq!(record.message.severity, Severity::INFO);
+        assert_eq!(record.message.msg, "systemd[1]: Started Journal Service.");
+        assert!(record.message.structured_data.is_empty());
+
+        let record = parse_record(
+            br"4,3,0,c;usb 1-1: string descriptor 0 read error: -22 \x1b",
+            boot_time(),
+        )
+        .unwrap();
+        assert_eq!(record.flags, "c");
+        assert_eq!(record.message.severity, Severity::WARNING);
+        assert_eq!(record.message.timestamp, Some(boot_time()));
+        assert_eq!(
+            record.message.msg,
+            r"usb 1-1: string descriptor 0 read error: -22 \x1b"
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            &b""[..],
+            b"6,339,5140900,- no separator",
+            b"6,339,5140900;missing flags",
+            b"x,339,5140900,-;bad pri",
+            b"6,-1,5140900,-;bad seq",
+            b"6,339,5140900,-;msg\nSUBSYSTEM=net",
+            b"6,339,5140900,-;msg\n SUBSYSTEM",
+            b"6,339,5140900,-;\xff",
+        ] {
+            assert_eq!(
+                parse_record(input, boot_time()),
+                Err(Error::InvalidKmsg),
+                "{input:?}"
+            );
+        }
+
+        assert_eq!(
+            parse_record(b"1000,1,1,-;bad facility", boot_time()),
+            Err(Error::BadFacility)
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Zero-copy `key=value` (logfmt) extraction from free-text MSG.
+//!
+//! ```
+//! use syslog::kv;
+//!
+//! let pairs = kv::pairs(r#"date=2023-04-07 devname="FG 100" action=deny msg="login \"admin\" failed""#)
+//!     .collect::<Vec<_>>();
+//! assert_eq!(pairs[1], ("devname", "FG 100"));
+//! assert_eq!(kv::unescape(pairs[3].1), r#"login "admin" failed"#);
+//!
+//! let parser = kv::Parser::new().pair_separator(',').kv_separator(':');
+//! let element = parser.structured_element("kv", "src:10.0.0.1, dst:10.0.0.2");
+//! assert_eq!(element.params, [("src", "10.0.0.1"), ("dst", "10.0.0.2")]);
+//! ```
+
+use alloc::borrow::Cow;
+
+use crate::{escape, StructuredElement};
+
+/// Extract pairs from `msg` with the default `Parser`, i.e. pairs are
+/// separated by whitespace, and keys and values by `=`.
+pub fn pairs(msg: &str) -> Pairs<'_> {
+    Parser::new().pairs(msg)
+}
+
+/// Configurable separators for extracting pairs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parser {
+    pair_separator: char,
+    kv_separator: char,
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Parser {
+            pair_separator: ' ',
+            kv_separator: '=',
+        }
+    }
+}
+
+impl Parser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The separator between pairs, whitespace by default. Whitespace around
+    /// keys and unquoted values is trimmed whatever the separator is.
+    pub fn pair_separator(mut self, separator: char) -> Self {
+        self.pair_separator = separator;
+        self
+    }
+
+    /// The separator between key and value, `=` by default.
+    pub fn kv_separator(mut self, separator: char) -> Self {
+        self.kv_separator = separator;
+        self
+    }
+
+    /// Iterate the pairs of `msg`.
+    ///
+    /// Words without a key/value separator are skipped, so pairs can be mixed
+    /// with free text. Values may be quoted with `"` or `'`, the quotes are
+    /// stripped, but escape sequences like `\"` are kept, see `unescape`. An
+    /// unterminated quoted value runs to the end of `msg`.
+    pub fn pairs<'a>(&self, msg: &'a str) -> Pairs<'a> {
+        Pairs {
+            rest: msg,
+            parser: *self,
+        }
+    }
+
+    /// Collect the pairs of `msg` as the params of a structured element, so
+    /// they can be handled like RFC 5424 structured data.
+    pub fn structured_element<'a>(&self, id: &'a str, msg: &'a str) -> StructuredElement<&'a str> {
+        StructuredElement {
+            id,
+            params: self.pairs(msg).collect(),
+        }
+    }
+
+    fn is_pair_separator(&self, ch: char) -> bool {
+        ch == self.pair_separator || ch.is_whitespace()
+    }
+}
+
+/// An iterator over the `(key, value)` pairs of a message, see
+/// `Parser::pairs`.
+#[derive(Clone, Debug)]
+pub struct Pairs<'a> {
+    rest: &'a str,
+    parser: Parser,
+}
+
+impl<'a> Iterator for Pairs<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.parser;
+
+        loop {
+            self.rest = self
+                .rest
+                .trim_start_matches(|ch| parser.is_pair_separator(ch));
+            if self.rest.is_empty() {
+                return None;
+            }
+
+            let key_end = self
+                .rest
+                .find(|ch| ch == parser.kv_separator || parser.is_pair_separator(ch) || ch == '"')
+                .unwrap_or(self.rest.len());
+            let key = &self.rest[..key_end];
+            let after_key = &self.rest[key_end..];
+
+            let Some(value) = after_key.strip_prefix(parser.kv_separator) else {
+                // a word of free text, or a stray quote
+                let skip = after_key.chars().next().map_or(0, char::len_utf8);
+                self.rest = &self.rest[key_end + skip..];
+                continue;
+            };
+            if key.is_empty() {
+                self.rest = value;
+                continue;
+            }
+
+            let value = if parser.pair_separator.is_whitespace() {
+                value
+            } else {
+                value.trim_start()
+            };
+            let value = match value.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let quoted = &value[1..];
+                    let end = find_closing_quote(quoted, quote);
+                    self.rest = quoted.get(end + 1..).unwrap_or_default();
+                    &quoted[..end]
+                }
+                _ => {
+                    let end = if parser.pair_separator.is_whitespace() {
+                        value.find(char::is_whitespace)
+                    } else {
+                        value.find(parser.pair_separator)
+                    }
+                    .unwrap_or(value.len());
+                    self.rest = &value[end..];
+                    value[..end].trim_end()
+                }
+            };
+
+            return Some((key, value));
+        }
+    }
+}
+
+/// The position of the closing `quote`, or the length of `s` if there is
+/// none.
+fn find_closing_quote(s: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (pos, ch) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == quote {
+            return pos;
+        }
+    }
+
+    s.len()
+}
+
+/// Resolve the backslash escapes of a value, e.g. `\"` and `\\`. Unknown
+/// escapes are kept as they are, except for `\n`, `\r` and `\t`.
+pub fn unescape(value: &str) -> Cow<'_, str> {
+    escape::unescape(value, |ch| match ch {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        '"' | '\'' | '\\' => Some(ch),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn logfmt() {
+        let input = r#"type=USER_LOGIN msg=audit(1680871920.654:412): pid=8449 uid=0 acct="root" exe="/usr/sbin/sshd" hostname=? addr=10.0.0.1 res=success'"#;
+        assert_eq!(
+            pairs(input).collect::<Vec<_>>(),
+            [
+                ("type", "USER_LOGIN"),
+                ("msg", "audit(1680871920.654:412):"),
+                ("pid", "8449"),
+                ("uid", "0"),
+                ("acct", "root"),
+                ("exe", "/usr/sbin/sshd"),
+                ("hostname", "?"),
+                ("addr", "10.0.0.1"),
+                ("res", "success'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        for (input, want) in [
+            (
+                r#"msg="say \"hi\"" path='C:\\temp' empty="" last=1"#,
+                &[
+                    ("msg", r#"say \"hi\""#),
+                    ("path", r"C:\\temp"),
+                    ("empty", ""),
+                    ("last", "1"),
+                ][..],
+            ),
+            (
+                "Accepted password for root from 10.0.0.1 port=22 ssh2",
+                &[("port", "22")],
+            ),
+            (
+                r#"unterminated="runs to the end"#,
+                &[("unterminated", "runs to the end")],
+            ),
+            (
+                "=novalue key= next=1 \"stray",
+                &[("key", ""), ("next", "1")],
+            ),
+            ("  ütf=ä€ \t x=y\n", &[("ütf", "ä€"), ("x", "y")]),
+            ("", &[]),
+        ] {
+            assert_eq!(pairs(input).collect::<Vec<_>>(), want, "{input}");
+        }
+    }
+
+    #[test]
+    fn separators() {
+        let parser = Parser::new().pair_separator(',').kv_separator(':');
+        assert_eq!(
+            parser
+                .pairs("src: 10.0.0.1 , dst:10.0.0.2,msg:\"a, b\",,")
+                .collect::<Vec<_>>(),
+            [("src", "10.0.0.1"), ("dst", "10.0.0.2"), ("msg", "a, b")]
+        );
+
+        let parser = Parser::new().pair_separator(';');
+        assert_eq!(
+            parser.pairs("user=John Doe;role=admin").collect::<Vec<_>>(),
+            [("user", "John Doe"), ("role", "admin")]
+        );
+    }
+
+    #[test]
+    fn unescaping() {
+        assert!(matches!(unescape("plain"), Cow::Borrowed("plain")));
+        assert_eq!(unescape(r#"say \"hi\"\n"#), "say \"hi\"\n");
+        assert_eq!(unescape(r"C:\\temp\x"), r"C:\temp\x");
+        assert_eq!(unescape("trailing\\"), "trailing\\");
+    }
+}
@@ -0,0 +1,212 @@
+//! Parser for IBM QRadar [Log Event Extended Format](https://www.ibm.com/docs/en/dsm?topic=leef-overview)
+//! payloads, which are usually carried in the MSG part of a syslog message.
+//!
+//! ```
+//! let raw = b"<134>1 2023-04-07T12:52:00Z fw01 - - - - LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=10.0.1.8^dst=10.0.0.5^proto=tcp";
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let event = syslog::leef::parse(msg.msg).unwrap();
+//! assert_eq!(event.vendor, "Lancope");
+//! assert_eq!(event.event_id, "41");
+//! assert_eq!(event.get("dst"), Some("10.0.0.5"));
+//!
+//! let element = event.into_structured_element("leef@41");
+//! assert_eq!(element.params[0], ("src", "10.0.1.8"));
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+
+use crate::escape::{split_header, unescape_header};
+use crate::{Error, StructuredElement};
+
+/// A parsed LEEF event, header fields without escape sequences borrow from
+/// the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeefEvent<'a> {
+    /// `1.0` or `2.0`
+    pub version: &'a str,
+    pub vendor: Cow<'a, str>,
+    pub product: Cow<'a, str>,
+    pub product_version: Cow<'a, str>,
+    pub event_id: Cow<'a, str>,
+    /// The attribute delimiter, always a tab for LEEF 1.0
+    pub delimiter: char,
+    /// The key=value attributes, in their original order.
+    pub attributes: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> LeefEvent<'a> {
+    /// Returns the value of the first attribute named `key`.
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+    }
+
+    /// Convert the attributes into a structured data element with `id`, so
+    /// they can be handled like the structured data of RFC 5424 messages.
+    pub fn into_structured_element(self, id: &'a str) -> StructuredElement<&'a str> {
+        StructuredElement {
+            id,
+            params: self.attributes,
+        }
+    }
+}
+
+/// Parse the LEEF payload in `msg`.
+///
+/// Like `cef::parse`, anything before `LEEF:` is ignored. The delimiter of
+/// LEEF 2.0 may be a single character, or a hex value like `x09` or `0x5E`,
+/// an empty delimiter field means tab. Empty attributes, e.g. after a
+/// trailing delimiter, are skipped.
+///
+/// Header fields may escape `\|` and `\\`, as in CEF.
+pub fn parse(msg: &str) -> Result<LeefEvent<'_>, Error> {
+    let start = msg.find("LEEF:").ok_or(Error::InvalidLeef)? + 5;
+    let payload = &msg[start..];
+
+    let mut fields = [""; 5];
+    let rest = split_header(payload, &mut fields).ok_or(Error::InvalidLeef)?;
+    let version = fields[0].trim();
+
+    let (delimiter, attributes) = match version {
+        "1.0" | "1" => ('\t', rest),
+        "2.0" | "2" => match rest.strip_prefix("||") {
+            Some(attributes) => ('|', attributes),
+            None => {
+                let (delimiter, attributes) = rest.split_once('|').ok_or(Error::InvalidLeef)?;
+                (parse_delimiter(delimiter)?, attributes)
+            }
+        },
+        _ => return Err(Error::InvalidLeef),
+    };
+
+    let attributes = attributes
+        .split(delimiter)
+        .filter(|attr| !attr.trim().is_empty())
+        .map(|attr| {
+            attr.split_once('=')
+                .filter(|(key, _)| !key.is_empty())
+                .ok_or(Error::InvalidLeef)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(LeefEvent {
+        version,
+        vendor: unescape_header(fields[1]),
+        product: unescape_header(fields[2]),
+        product_version: unescape_header(fields[3]),
+        event_id: unescape_header(fields[4]),
+        delimiter,
+        attributes,
+    })
+}
+
+fn parse_delimiter(s: &str) -> Result<char, Error> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (None, _) => return Ok('\t'),
+        (Some(ch), None) => return Ok(ch),
+        _ => {}
+    }
+
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("x"))
+        .or_else(|| s.strip_prefix("0X"))
+        .or_else(|| s.strip_prefix("X"))
+        .ok_or(Error::InvalidLeef)?;
+
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or(Error::InvalidLeef)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leef_1() {
+        let event = parse(
+            "Jan 18 11:07:53 host LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0\tdst=172.50.123.1\tsev=5\tmsg=a=b|c\t",
+        )
+        .unwrap();
+
+        assert_eq!(
+            event,
+            LeefEvent {
+                version: "1.0",
+                vendor: "Microsoft".into(),
+                product: "MSExchange".into(),
+                product_version: "4.0 SP1".into(),
+                event_id: "15345".into(),
+                delimiter: '\t',
+                attributes: vec![
+                    ("src", "192.0.2.0"),
+                    ("dst", "172.50.123.1"),
+                    ("sev", "5"),
+                    ("msg", "a=b|c"),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn leef_2_delimiters() {
+        for (input, delimiter) in [
+            ("LEEF:2.0|V|P|1.0|41|^|src=1^dst=2", '^'),
+            ("LEEF:2.0|V|P|1.0|41|x5E|src=1^dst=2", '^'),
+            ("LEEF:2.0|V|P|1.0|41|0x5e|src=1^dst=2", '^'),
+            ("LEEF:2.0|V|P|1.0|41|x09|src=1\tdst=2", '\t'),
+            ("LEEF:2.0|V|P|1.0|41||src=1\tdst=2", '\t'),
+            ("LEEF:2.0|V|P|1.0|41|||src=1|dst=2|", '|'),
+        ] {
+            let event = parse(input).unwrap();
+            assert_eq!(event.delimiter, delimiter, "{input}");
+            assert_eq!(event.attributes, [("src", "1"), ("dst", "2")], "{input}");
+        }
+    }
+
+    #[test]
+    fn escaped_header() {
+        let event = parse(r"LEEF:2.0|A\|B Corp|C\\D|1.0|41|^|src=1").unwrap();
+        assert_eq!(event.vendor, "A|B Corp");
+        assert_eq!(event.product, r"C\D");
+        assert_eq!(event.product_version, "1.0");
+        assert_eq!(event.event_id, "41");
+        assert_eq!(event.delimiter, '^');
+        assert_eq!(event.attributes, [("src", "1")]);
+    }
+
+    #[test]
+    fn structured_element() {
+        let event = parse("LEEF:2.0|V|P|1.0|41|^|src=1^dst=2").unwrap();
+        assert_eq!(
+            event.into_structured_element("leef"),
+            StructuredElement {
+                id: "leef",
+                params: vec![("src", "1"), ("dst", "2")],
+            }
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        for input in [
+            "",
+            "LEEF:1.0|V|P|1.0",
+            "LEEF:3.0|V|P|1.0|41|src=1",
+            "LEEF:1.0|V|P|1.0|41|src",
+            "LEEF:1.0|V|P|1.0|41|=1",
+            "LEEF:2.0|V|P|1.0|41|src=1",
+            "LEEF:2.0|V|P|1.0|41|xZZ|src=1",
+            "LEEF:2.0|V|P|1.0|41|ab|src=1",
+        ] {
+            assert_eq!(parse(input), Err(Error::InvalidLeef), "{input}");
+        }
+    }
+}
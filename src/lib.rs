@@ -8,7 +8,7 @@
 //!
 //! # Example
 //!
-//! A simple syslog server
+//! A simple syslog server, see the `server` module for a complete one
 //!
 //! ```no_run
 //! use syslog::Message;
@@ -30,18 +30,65 @@
 //!    message. Rust doesn't have a convenient way to only treat *some* of a buffer as utf-8,
 //!    so I'm just not supporting that. Most "real" syslog servers barf on it anway.
 //!
+//! # Features
+//!
+//!  * `std` (default): implements `std::error::Error`, and enables the `stream`, `sender` and
+//!    `server` modules and `rfc3164::Resolver::resolve_now`.
+//!    Without it, the crate is `no_std` and only needs `alloc`.
+//!  * `simd`: use [memchr](https://docs.rs/memchr) for scanning long fields.
+//!  * `time` and `jiff`: convert `Timestamp` into [time](https://docs.rs/time) and
+//!    [jiff](https://docs.rs/jiff) types, see `rfc5424::parse_message_as`.
+//!  * `gelf`: the `gelf` module, for converting messages from and to Graylog's GELF.
+//!  * `serde_json`: `Message::json`, for `@cee:` and plain JSON bodies in MSG.
+//!  * `tls`: the `tls` module, for syslog over TLS (RFC 5425) with `Sender::tls` and `Server::tls`.
+//!
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
+pub mod audit;
+pub mod cef;
 mod error;
+mod escape;
 mod facility;
+#[cfg(feature = "std")]
+mod framing;
+#[cfg(feature = "gelf")]
+pub mod gelf;
+mod host;
+pub mod journal;
+#[cfg(feature = "serde_json")]
+pub mod json;
+pub mod kmsg;
+pub mod kv;
+pub mod leef;
 mod message;
 mod procid;
+pub mod rfc3164;
 pub mod rfc5424;
+mod scan;
+#[cfg(feature = "std")]
+pub mod sender;
+#[cfg(feature = "std")]
+pub mod server;
 mod severity;
+#[cfg(feature = "std")]
+pub mod stream;
 mod structured_data;
+pub mod timestamp;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod vendor;
 
 pub use error::Error;
 pub use facility::Facility;
+pub use host::Host;
 pub use message::{Message, Protocol};
 pub use procid::ProcId;
 pub use severity::Severity;
-pub use structured_data::StructuredElement;
+pub use structured_data::{
+    RawElements, RawParams, RawStructuredData, RawStructuredElement, Scratch, StructuredDataRef,
+    StructuredElement, StructuredElementRef,
+};
+pub use timestamp::Timestamp;
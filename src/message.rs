@@ -1,30 +1,102 @@
 //! In-memory representation of a single Syslog message.
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
 use chrono::{DateTime, FixedOffset};
 
 use crate::facility;
+use crate::host::Host;
 use crate::procid::ProcId;
 use crate::severity;
 use crate::structured_data::StructuredElement;
+use crate::Error;
 
+/// Where a message comes from, new sources may add variants.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum Protocol {
     RFC3164,
     RFC5424(u32),
+    /// Linux `/dev/kmsg` records, see the `kmsg` module
+    Kmsg,
+    /// systemd journal entries, see the `journal` module
+    Journal,
+    /// Graylog Extended Log Format, see the `gelf` module
+    Gelf,
 }
 
 /// A RFC5424-protocol syslog message
+///
+/// `D` is the container of the structured data, see `rfc5424::parse_message_in`
+/// for parsing it into a reusable `Scratch` instead of `Vec`s.
+///
+/// `T` is the type of the timestamp, see `rfc5424::parse_message_as`.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Message<S: AsRef<str> + Ord + PartialEq + Clone> {
+pub struct Message<
+    S: AsRef<str> + Ord + PartialEq + Clone,
+    D = Vec<StructuredElement<S>>,
+    T = DateTime<FixedOffset>,
+> {
     pub severity: severity::Severity,
     pub facility: facility::Facility,
     pub protocol: Protocol,
-    pub timestamp: Option<DateTime<FixedOffset>>,
+    pub timestamp: Option<T>,
     pub hostname: Option<S>,
     pub appname: Option<S>,
     pub procid: Option<ProcId<S>>,
     pub msgid: Option<S>,
     // NOTE: param value is not escaped
-    pub structured_data: Vec<StructuredElement<S>>,
+    pub structured_data: D,
     pub msg: S,
 }
+
+impl<S: AsRef<str> + Ord + PartialEq + Clone, D, T> Message<S, D, T> {
+    /// Classify the hostname as an IP address, FQDN or simple hostname,
+    /// `None` if it is the NILVALUE.
+    pub fn host(&self) -> Option<Result<Host<'_>, Error>> {
+        self.hostname
+            .as_ref()
+            .map(|hostname| Host::parse(hostname.as_ref()))
+    }
+
+    /// Parse MSG as a JSON object, with or without the `@cee:` cookie, see
+    /// `json::extract`. `None` means MSG is plain text.
+    #[cfg(feature = "serde_json")]
+    pub fn json(&self) -> Option<crate::json::JsonBody> {
+        crate::json::extract(self.msg.as_ref())
+    }
+}
+
+impl<T> Message<&str, Vec<StructuredElement<&str>>, T> {
+    /// Copy the borrowed fields, so the message can outlive the buffer it
+    /// was parsed from.
+    pub fn into_owned(self) -> Message<String, Vec<StructuredElement<String>>, T> {
+        Message {
+            severity: self.severity,
+            facility: self.facility,
+            protocol: self.protocol,
+            timestamp: self.timestamp,
+            hostname: self.hostname.map(ToString::to_string),
+            appname: self.appname.map(ToString::to_string),
+            procid: self.procid.map(|procid| match procid {
+                ProcId::PID(pid) => ProcId::PID(pid),
+                ProcId::Name(name) => ProcId::Name(name.to_string()),
+            }),
+            msgid: self.msgid.map(ToString::to_string),
+            structured_data: self
+                .structured_data
+                .into_iter()
+                .map(|element| StructuredElement {
+                    id: element.id.to_string(),
+                    params: element
+                        .params
+                        .into_iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                })
+                .collect(),
+            msg: self.msg.to_string(),
+        }
+    }
+}
@@ -0,0 +1,516 @@
+//! Helpers for [RFC 3164](https://tools.ietf.org/html/rfc3164) BSD syslog messages.
+//!
+//! BSD timestamps (`Oct 11 22:14:15`) carry neither a year nor a timezone, so
+//! they must be resolved against a reference time before they can be stored in
+//! `Message::timestamp`.
+//!
+//! ```
+//! use chrono::{FixedOffset, TimeZone, Utc};
+//! use syslog::rfc3164::{parse_timestamp, Resolver};
+//!
+//! let ts = parse_timestamp(b"Dec 31 23:59:59 mymachine su: hello", &mut 0).unwrap();
+//!
+//! let resolver = Resolver::new(FixedOffset::east_opt(8 * 3600).unwrap());
+//! let now = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 10).unwrap();
+//! let datetime = resolver.resolve(&ts, now).unwrap();
+//! assert_eq!(datetime.to_rfc3339(), "2023-12-31T23:59:59+08:00");
+//! ```
+
+use alloc::vec::Vec;
+
+use chrono::{DateTime, Datelike, FixedOffset, LocalResult, NaiveDate, TimeZone, Timelike, Utc};
+
+use crate::rfc5424::{push_field, push_number, push_pri};
+use crate::{Error, Facility, Message, ProcId, Protocol, Severity};
+
+pub(crate) const MONTHS: [&[u8; 3]; 12] = [
+    b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov", b"Dec",
+];
+
+/// A BSD syslog timestamp, e.g. `Oct 11 22:14:15`, which has no year and no
+/// timezone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BsdTimestamp {
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+/// Parse `Mmm dd hh:mm:ss` at `offset`, the day may be padded with a space
+/// (`Oct  1`) or a zero (`Oct 01`). On success `offset` points right after the
+/// seconds.
+pub fn parse_timestamp(buf: &[u8], offset: &mut usize) -> Result<BsdTimestamp, Error> {
+    let input = buf
+        .get(*offset..*offset + 15)
+        .ok_or(Error::UnexpectedEndOfInput)?;
+
+    let month = MONTHS
+        .iter()
+        .position(|name| name[..] == input[..3])
+        .ok_or(Error::InvalidTimestamp)? as u8
+        + 1;
+
+    if input[3] != b' ' || input[9] != b':' || input[12] != b':' {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let day = match input[4] {
+        b' ' => digits(&input[5..6])?,
+        _ => digits(&input[4..6])?,
+    };
+    if input[6] != b' ' {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let hour = digits(&input[7..9])?;
+    let minute = digits(&input[10..12])?;
+    let second = digits(&input[13..15])?;
+    if day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    *offset += 15;
+
+    Ok(BsdTimestamp {
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    })
+}
+
+fn digits(buf: &[u8]) -> Result<u8, Error> {
+    buf.iter().try_fold(0u8, |acc, ch| {
+        if ch.is_ascii_digit() {
+            Ok(acc * 10 + (ch - b'0'))
+        } else {
+            Err(Error::InvalidTimestamp)
+        }
+    })
+}
+
+/// Infers the year and timezone of `BsdTimestamp`s.
+///
+/// The year is taken from the reference time (in the resolver's timezone),
+/// except around new year: a December message received in January belongs to
+/// the previous year, and a January message received in December (a sender
+/// whose clock runs ahead) to the next one.
+///
+/// `tz` can be any chrono `TimeZone`, e.g. `Utc`, a `FixedOffset` or
+/// `chrono::Local`, DST transitions are handled by the timezone itself.
+#[derive(Clone, Debug)]
+pub struct Resolver<Tz> {
+    tz: Tz,
+}
+
+impl<Tz: TimeZone> Resolver<Tz> {
+    pub fn new(tz: Tz) -> Self {
+        Resolver { tz }
+    }
+
+    /// Resolve `ts` relative to `now`, usually the time the message was
+    /// received.
+    ///
+    /// `Error::InvalidTimestamp` is returned if the date doesn't exist in the
+    /// inferred year (e.g. `Feb 29`), or the time falls into a DST gap. An
+    /// ambiguous local time (DST fold) resolves to the earlier one.
+    pub fn resolve(
+        &self,
+        ts: &BsdTimestamp,
+        now: DateTime<Utc>,
+    ) -> Result<DateTime<FixedOffset>, Error> {
+        let now = now.with_timezone(&self.tz);
+
+        let year = match (now.month(), ts.month) {
+            (1, 12) => now.year() - 1,
+            (12, 1) => now.year() + 1,
+            _ => now.year(),
+        };
+
+        // chrono has no representation for leap seconds outside of nanos
+        let (second, nanos) = if ts.second == 60 {
+            (59, 1_000_000_000)
+        } else {
+            (ts.second, 0)
+        };
+
+        let naive = NaiveDate::from_ymd_opt(year, ts.month as u32, ts.day as u32)
+            .and_then(|date| {
+                date.and_hms_nano_opt(ts.hour as u32, ts.minute as u32, second as u32, nanos)
+            })
+            .ok_or(Error::InvalidTimestamp)?;
+
+        match self.tz.from_local_datetime(&naive) {
+            LocalResult::Single(datetime) | LocalResult::Ambiguous(datetime, _) => {
+                Ok(datetime.fixed_offset())
+            }
+            LocalResult::None => Err(Error::InvalidTimestamp),
+        }
+    }
+
+    /// Resolve `ts` relative to the current system time.
+    #[cfg(feature = "std")]
+    pub fn resolve_now(&self, ts: &BsdTimestamp) -> Result<DateTime<FixedOffset>, Error> {
+        self.resolve(ts, DateTime::from(std::time::SystemTime::now()))
+    }
+}
+
+/// Parse a BSD syslog message, `<PRI>TIMESTAMP HOSTNAME TAG: MSG`, whose
+/// timestamp is resolved with `resolver` relative to `now`.
+///
+/// Real-world messages rarely follow RFC 3164 to the letter, so everything
+/// after PRI is optional. Without a timestamp, e.g. when relayed as is, the
+/// hostname is assumed to be missing too. The first word is the TAG if it ends
+/// with `:`, like `su:` or `sshd[8449]:`, and the PID in brackets becomes
+/// `procid`. MSGID and structured data are always empty.
+pub fn parse_message<'a, Tz: TimeZone>(
+    buf: &'a [u8],
+    resolver: &Resolver<Tz>,
+    now: DateTime<Utc>,
+) -> Result<Message<&'a str>, Error> {
+    let buf = core::str::from_utf8(buf).map_err(|_| Error::InvalidUtf8)?;
+
+    let rest = buf.strip_prefix('<').ok_or(Error::ExpectedChar('<'))?;
+    let end = rest.find('>').ok_or(Error::UnexpectedEndOfInput)?;
+    if end == 0 || end > 3 {
+        return Err(Error::BadFacility);
+    }
+    let mut prival = 0i32;
+    for ch in rest[..end].bytes() {
+        if !ch.is_ascii_digit() {
+            return Err(Error::ExpectedChar(ch as char));
+        }
+        prival = prival * 10 + (ch - b'0') as i32;
+    }
+    let severity = Severity::try_from(prival & 0x7)?;
+    let facility = Facility::try_from(prival >> 3)?;
+    let mut rest = &rest[end + 1..];
+
+    let mut offset = 0;
+    let timestamp = match parse_timestamp(rest.as_bytes(), &mut offset) {
+        Ok(ts) => {
+            rest = rest[offset..].strip_prefix(' ').unwrap_or(&rest[offset..]);
+            Some(resolver.resolve(&ts, now)?)
+        }
+        Err(_) => None,
+    };
+
+    let mut hostname = None;
+    if timestamp.is_some() {
+        if let Some((word, after)) = rest.split_once(' ') {
+            if !word.is_empty() && parse_tag(word).is_none() {
+                hostname = Some(word);
+                rest = after;
+            }
+        }
+    }
+
+    let (mut appname, mut procid) = (None, None);
+    let (word, after) = rest.split_once(' ').unwrap_or((rest, ""));
+    if let Some((name, pid)) = parse_tag(word) {
+        appname = Some(name);
+        procid = pid.map(ProcId::from);
+        rest = after;
+    }
+
+    Ok(Message {
+        severity,
+        facility,
+        protocol: Protocol::RFC3164,
+        timestamp,
+        hostname,
+        appname,
+        procid,
+        msgid: None,
+        structured_data: Vec::new(),
+        msg: rest,
+    })
+}
+
+/// Split a TAG like `su:` or `sshd[8449]:` into the name and the PID.
+fn parse_tag(word: &str) -> Option<(&str, Option<&str>)> {
+    let tag = word.strip_suffix(':')?;
+    let (name, pid) = match tag.strip_suffix(']') {
+        Some(tag) => {
+            let (name, pid) = tag.split_once('[')?;
+            (name, Some(pid).filter(|pid| !pid.is_empty()))
+        }
+        None => (tag, None),
+    };
+
+    if name.is_empty() || name.contains(['[', ']']) {
+        return None;
+    }
+
+    Some((name, pid))
+}
+
+/// Append `msg` to `out` in the BSD format, i.e.
+/// `<PRI>Mmm dd hh:mm:ss HOSTNAME APPNAME[PROCID]: MSG`.
+///
+/// The timestamp is written in its own offset, which is lost, like the year
+/// and the sub-second precision. The timestamp, hostname and tag are left out
+/// when they are missing, MSGID and structured data are never written.
+/// Characters other than printable US-ASCII are replaced by `_` in the
+/// hostname and tag, which are truncated to 255 and 48 characters.
+pub fn format_message<S>(msg: &Message<S>, out: &mut Vec<u8>)
+where
+    S: AsRef<str> + Ord + PartialEq + Clone,
+{
+    push_pri(msg.severity, msg.facility, out);
+
+    if let Some(timestamp) = &msg.timestamp {
+        let local = timestamp.naive_local();
+        out.extend_from_slice(MONTHS[local.month0() as usize]);
+        out.push(b' ');
+        if local.day() < 10 {
+            out.push(b' ');
+        }
+        push_number(local.day() as u64, out);
+        // chrono keeps leap seconds in the nanos
+        let second = local.second() + (local.nanosecond() >= 1_000_000_000) as u32;
+        for (sep, n) in [(b' ', local.hour()), (b':', local.minute()), (b':', second)] {
+            out.push(sep);
+            if n < 10 {
+                out.push(b'0');
+            }
+            push_number(n as u64, out);
+        }
+        out.push(b' ');
+    }
+
+    if let Some(hostname) = msg.hostname.as_ref().filter(|h| !h.as_ref().is_empty()) {
+        push_field(Some(hostname.as_ref()), 255, out);
+        out.push(b' ');
+    }
+
+    if let Some(appname) = msg.appname.as_ref().filter(|a| !a.as_ref().is_empty()) {
+        push_field(Some(appname.as_ref()), 48, out);
+        match &msg.procid {
+            Some(ProcId::PID(pid)) => {
+                out.push(b'[');
+                if *pid < 0 {
+                    out.push(b'-');
+                }
+                push_number(pid.unsigned_abs() as u64, out);
+                out.push(b']');
+            }
+            Some(ProcId::Name(name)) => {
+                out.push(b'[');
+                push_field(Some(name.as_ref()), 128, out);
+                out.push(b']');
+            }
+            None => {}
+        }
+        out.extend_from_slice(b": ");
+    }
+
+    out.extend_from_slice(msg.msg.as_ref().as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn timestamp() {
+        for (input, want) in [
+            ("Oct 11 22:14:15", (10, 11, 22, 14, 15)),
+            ("Jan  1 00:00:00", (1, 1, 0, 0, 0)),
+            ("Feb 01 23:59:60", (2, 1, 23, 59, 60)),
+        ] {
+            let mut offset = 0;
+            let ts = parse_timestamp(input.as_bytes(), &mut offset).unwrap();
+            assert_eq!(offset, 15);
+            assert_eq!(
+                (ts.month, ts.day, ts.hour, ts.minute, ts.second),
+                want,
+                "{input}"
+            );
+        }
+
+        for input in [
+            "Oct 11 22:14:1",
+            "oct 11 22:14:15",
+            "Oct 32 22:14:15",
+            "Oct  0 22:14:15",
+            "Oct 11 24:14:15",
+            "Oct 11T22:14:15",
+            "Oct 1a 22:14:15",
+        ] {
+            assert!(
+                parse_timestamp(input.as_bytes(), &mut 0).is_err(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn year_rollover() {
+        let resolver = Resolver::new(Utc);
+        let dec = parse_timestamp(b"Dec 31 23:59:59", &mut 0).unwrap();
+        let jan = parse_timestamp(b"Jan  1 00:00:01", &mut 0).unwrap();
+        let jun = parse_timestamp(b"Jun 15 12:00:00", &mut 0).unwrap();
+
+        for (ts, now, want) in [
+            (&dec, utc(2024, 1, 1, 0), "2023-12-31T23:59:59+00:00"),
+            (&dec, utc(2023, 12, 31, 23), "2023-12-31T23:59:59+00:00"),
+            (&jan, utc(2023, 12, 31, 23), "2024-01-01T00:00:01+00:00"),
+            (&jan, utc(2024, 1, 2, 0), "2024-01-01T00:00:01+00:00"),
+            (&jun, utc(2024, 1, 1, 0), "2024-06-15T12:00:00+00:00"),
+        ] {
+            assert_eq!(resolver.resolve(ts, now).unwrap().to_rfc3339(), want);
+        }
+    }
+
+    #[test]
+    fn timezone() {
+        // 2023-12-31T20:00:00Z is already 2024 in +08:00
+        let resolver = Resolver::new(FixedOffset::east_opt(8 * 3600).unwrap());
+        let ts = parse_timestamp(b"Jan  1 04:00:00", &mut 0).unwrap();
+        let datetime = resolver.resolve(&ts, utc(2023, 12, 31, 20)).unwrap();
+        assert_eq!(datetime.to_rfc3339(), "2024-01-01T04:00:00+08:00");
+
+        let resolver = Resolver::new(FixedOffset::west_opt(5 * 3600).unwrap());
+        let ts = parse_timestamp(b"Dec 31 22:00:00", &mut 0).unwrap();
+        let datetime = resolver.resolve(&ts, utc(2024, 1, 1, 3)).unwrap();
+        assert_eq!(datetime.to_rfc3339(), "2023-12-31T22:00:00-05:00");
+    }
+
+    #[test]
+    fn invalid_date() {
+        let resolver = Resolver::new(Utc);
+        let ts = parse_timestamp(b"Feb 29 12:00:00", &mut 0).unwrap();
+
+        assert!(resolver.resolve(&ts, utc(2024, 3, 1, 0)).is_ok());
+        assert_eq!(
+            resolver.resolve(&ts, utc(2023, 3, 1, 0)),
+            Err(Error::InvalidTimestamp)
+        );
+    }
+
+    #[test]
+    fn messages() {
+        let resolver = Resolver::new(Utc);
+        let now = utc(2003, 10, 12, 0);
+
+        let msg = parse_message(
+            b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick on /dev/pts/8",
+            &resolver,
+            now,
+        )
+        .unwrap();
+        assert_eq!(msg.facility, Facility::AUTH);
+        assert_eq!(msg.severity, Severity::CRIT);
+        assert_eq!(msg.protocol, Protocol::RFC3164);
+        assert_eq!(
+            msg.timestamp.unwrap().to_rfc3339(),
+            "2003-10-11T22:14:15+00:00"
+        );
+        assert_eq!(msg.hostname, Some("mymachine"));
+        assert_eq!(msg.appname, Some("su"));
+        assert_eq!(msg.procid, None);
+        assert_eq!(msg.msg, "'su root' failed for lonvick on /dev/pts/8");
+
+        for (input, hostname, appname, procid, text) in [
+            (
+                "<13>Oct  1 22:14:15 sshd[8449]: Accepted publickey",
+                None,
+                Some("sshd"),
+                Some(ProcId::PID(8449)),
+                "Accepted publickey",
+            ),
+            (
+                "<13>Oct 11 22:14:15 host message without tag",
+                Some("host"),
+                None,
+                None,
+                "message without tag",
+            ),
+            (
+                "<13>Oct 11 22:14:15 host app[worker]:",
+                Some("host"),
+                Some("app"),
+                Some(ProcId::Name("worker")),
+                "",
+            ),
+            (
+                "<13>app: no timestamp",
+                None,
+                Some("app"),
+                None,
+                "no timestamp",
+            ),
+            ("<13>just text", None, None, None, "just text"),
+            ("<13>", None, None, None, ""),
+        ] {
+            let msg = parse_message(input.as_bytes(), &resolver, now).unwrap();
+            assert_eq!(msg.hostname, hostname, "{input}");
+            assert_eq!(msg.appname, appname, "{input}");
+            assert_eq!(msg.procid, procid, "{input}");
+            assert_eq!(msg.msg, text, "{input}");
+        }
+
+        for (input, err) in [
+            (&b"Oct 11 22:14:15 no pri"[..], Error::ExpectedChar('<')),
+            (b"<13", Error::UnexpectedEndOfInput),
+            (b"<>", Error::BadFacility),
+            (b"<1x>", Error::ExpectedChar('x')),
+            (b"<999>", Error::BadFacility),
+            (b"<13>Feb 30 22:14:15 host", Error::InvalidTimestamp),
+            (b"<13>\xff", Error::InvalidUtf8),
+        ] {
+            assert_eq!(parse_message(input, &resolver, now), Err(err), "{input:?}");
+        }
+    }
+
+    #[test]
+    fn format() {
+        let timestamp = DateTime::parse_from_rfc3339("2003-10-01T22:14:15.003+02:00").unwrap();
+        let mut msg = Message {
+            severity: Severity::CRIT,
+            facility: Facility::AUTH,
+            protocol: Protocol::RFC3164,
+            timestamp: Some(timestamp),
+            hostname: Some("mymachine"),
+            appname: Some("su"),
+            procid: Some(ProcId::PID(123)),
+            msgid: Some("ID47"),
+            structured_data: alloc::vec![],
+            msg: "'su root' failed for lonvick on /dev/pts/8",
+        };
+
+        let mut out = Vec::new();
+        format_message(&msg, &mut out);
+        assert_eq!(
+            core::str::from_utf8(&out).unwrap(),
+            "<34>Oct  1 22:14:15 mymachine su[123]: 'su root' failed for lonvick on /dev/pts/8"
+        );
+
+        msg.timestamp = Some(DateTime::parse_from_rfc3339("1990-12-31T23:59:60Z").unwrap());
+        msg.hostname = None;
+        msg.procid = None;
+        out.clear();
+        format_message(&msg, &mut out);
+        assert_eq!(
+            core::str::from_utf8(&out).unwrap(),
+            "<34>Dec 31 23:59:60 su: 'su root' failed for lonvick on /dev/pts/8"
+        );
+
+        msg.timestamp = None;
+        msg.appname = None;
+        out.clear();
+        format_message(&msg, &mut out);
+        assert_eq!(
+            core::str::from_utf8(&out).unwrap(),
+            "<34>'su root' failed for lonvick on /dev/pts/8"
+        );
+    }
+}
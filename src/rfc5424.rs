@@ -1,188 +1,163 @@
-use chrono::{DateTime, FixedOffset, NaiveDate};
+use alloc::vec;
+use alloc::vec::Vec;
 
-use crate::message::Protocol;
-use crate::{Error, Facility, Message, ProcId, Severity, StructuredElement};
+use chrono::{DateTime, FixedOffset};
 
+use crate::message::Protocol;
+use crate::scan::{find, find2};
+use crate::timestamp::{parse_lenient_at, parse_rfc3339};
+use crate::{
+    Error, Facility, Message, ProcId, RawStructuredData, Scratch, Severity, StructuredDataRef,
+    StructuredElement, Timestamp,
+};
+
+// Parse rfc3339, see `timestamp::parse` for how edge cases are handled.
+//
+// https://datatracker.ietf.org/doc/html/rfc3339
 #[inline]
-fn convert_2_digits(digits: &[u8]) -> u32 {
-    let bytes: [u8; 2] = digits.try_into().unwrap();
-    let chunk = u16::from_ne_bytes(bytes) as u32;
-    let lower = (chunk & 0x0f00) >> 8;
-    let upper = (chunk & 0x000f) * 10;
-
-    lower + upper
+pub fn parse_timestamp(buf: &[u8], offset: &mut usize) -> Result<DateTime<FixedOffset>, Error> {
+    DateTime::try_from(parse_rfc3339(buf, offset)?)
 }
 
+// Most fields are short, see `scan` for when SIMD kicks in.
 #[inline]
-fn convert_4_digits(digits: &[u8]) -> u32 {
-    let bytes: [u8; 4] = digits.try_into().unwrap();
-    let mut chunk = u32::from_ne_bytes(bytes);
+fn take_until_whitespace<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
+    let pos = find(buf, *offset, b' ').ok_or(Error::UnexpectedEndOfInput)?;
+    let value = unsafe { core::str::from_utf8_unchecked(&buf[*offset..pos]) };
+    *offset = pos;
 
-    let mut lower = (chunk & 0x0f000f00) >> 8;
-    let mut upper = (chunk & 0x000f000f) * 10;
+    Ok(value)
+}
 
-    chunk = lower + upper;
+/// The byte at `offset`, or `Error::UnexpectedEndOfInput` if the input is
+/// truncated before it.
+#[inline]
+fn byte_at(buf: &[u8], offset: usize) -> Result<u8, Error> {
+    buf.get(offset).copied().ok_or(Error::UnexpectedEndOfInput)
+}
 
-    lower = (chunk & 0x00ff0000) >> 16;
-    upper = (chunk & 0x000000ff) * 100;
+/// Where the `Vec`s of structured data come from. `parse_message` simply
+/// allocates them, while `BatchParser` reuses them across messages.
+trait Buffers<'a> {
+    fn elements(&mut self) -> Vec<StructuredElement<&'a str>>;
 
-    lower + upper
+    fn params(&mut self) -> Vec<(&'a str, &'a str)>;
 }
 
-#[inline]
-fn to_datetime(
-    year: i32,
-    month: u32,
-    day: u32,
-    hour: u32,
-    minute: u32,
-    second: u32,
-    nanos: u32,
-    offset: i32,
-) -> Result<DateTime<FixedOffset>, Error> {
-    let offset = FixedOffset::east_opt(offset).ok_or(Error::OutOfRangeTimezone)?;
-    let datetime = NaiveDate::from_ymd_opt(year, month, day)
-        .ok_or(Error::InvalidTimestamp)?
-        .and_hms_nano_opt(hour, minute, second, nanos)
-        .ok_or(Error::InvalidTimestamp)?;
-
-    // DateTime::from_local() takes a lot time. it's almost 40% of the
-    // timestamp benchmark
-    #[allow(deprecated)]
-    Ok(DateTime::from_local(datetime, offset))
-}
-
-// Parse rfc3339
-//
-// https://datatracker.ietf.org/doc/html/rfc3339
-#[inline]
-pub fn parse_timestamp(buf: &[u8], offset: &mut usize) -> Result<DateTime<FixedOffset>, Error> {
-    let len = buf.len();
-    // 20 is the length of `1990-12-31T23:59:60Z`
-    if len - *offset < 20 {
-        return Err(Error::InvalidTimestamp);
-    }
+struct Alloc;
 
-    let year = convert_4_digits(&buf[*offset..*offset + 4]) as i32;
+impl<'a> Buffers<'a> for Alloc {
+    #[inline]
+    fn elements(&mut self) -> Vec<StructuredElement<&'a str>> {
+        // 4 is RawVec::MIN_NON_ZERO_CAP
+        Vec::with_capacity(4)
+    }
 
-    if buf[*offset + 4] != b'-' {
-        return Err(Error::InvalidTimestamp);
+    #[inline]
+    fn params(&mut self) -> Vec<(&'a str, &'a str)> {
+        Vec::with_capacity(4)
     }
+}
 
-    *offset += 5;
-    let month = convert_2_digits(&buf[*offset..*offset + 2]);
+/// What the STRUCTURED-DATA part of a message is parsed into.
+trait ParseStructuredData<'a> {
+    type Output;
 
-    if buf[*offset + 2] != b'-' {
-        return Err(Error::InvalidTimestamp);
-    }
+    /// No structured data, aka `-`
+    fn nil(self, buf: &'a [u8]) -> Self::Output;
 
-    *offset += 3;
-    let day = convert_2_digits(&buf[*offset..*offset + 2]);
+    fn parse(self, buf: &'a [u8], offset: &mut usize) -> Result<Self::Output, Error>;
+}
 
-    if buf[*offset + 2] != b'T' {
-        return Err(Error::InvalidTimestamp);
-    }
-    *offset += 3;
+impl<'a, B: Buffers<'a>> ParseStructuredData<'a> for &mut B {
+    type Output = Vec<StructuredElement<&'a str>>;
 
-    let hour = convert_2_digits(&buf[*offset..*offset + 2]);
-    if buf[*offset + 2] != b':' {
-        return Err(Error::InvalidTimestamp);
+    #[inline]
+    fn nil(self, _buf: &'a [u8]) -> Self::Output {
+        Vec::new()
     }
-    *offset += 3;
 
-    let minute = convert_2_digits(&buf[*offset..*offset + 2]);
-    if buf[*offset + 2] != b':' {
-        return Err(Error::InvalidTimestamp);
+    #[inline]
+    fn parse(self, buf: &'a [u8], offset: &mut usize) -> Result<Self::Output, Error> {
+        parse_structured_data(buf, offset, self)
     }
-    *offset += 3;
+}
 
-    let second = convert_2_digits(&buf[*offset..*offset + 2]);
-    *offset += 2;
+impl<'a> ParseStructuredData<'a> for &'a mut Scratch {
+    type Output = StructuredDataRef<'a>;
 
-    let next_char = buf[*offset];
-    let nanos = if next_char == b'.' || next_char == b',' {
-        let mut nanos = 0u32;
-        let mut count = 0;
-        *offset += 1;
-        let end = std::cmp::min(*offset + 9, len);
-        for ch in &buf[*offset..end] {
-            if !ch.is_ascii_digit() {
-                break;
-            }
+    #[inline]
+    fn nil(self, buf: &'a [u8]) -> Self::Output {
+        self.clear();
+        StructuredDataRef::new(buf, self)
+    }
 
-            count += 1;
-            nanos = (nanos * 10) + (ch - b'0') as u32;
-        }
+    #[inline]
+    fn parse(self, buf: &'a [u8], offset: &mut usize) -> Result<Self::Output, Error> {
+        self.clear();
+        parse_structured_data_in(buf, offset, self)?;
+        Ok(StructuredDataRef::new(buf, self))
+    }
+}
 
-        *offset += count;
-        nanos * 10u32.pow(9 - count as u32)
-    } else if next_char == b'z' || next_char == b'Z' {
-        // no nanos, no offset. e.g. `1990-12-31T23:59:60Z`
-        return to_datetime(year, month, day, hour, minute, second, 0, 0);
-    } else {
-        0
-    };
+/// Only record the span of the structured data, elements are parsed on demand.
+struct Lazy;
 
-    let sign = match buf[*offset] {
-        b'z' | b'Z' => {
-            // no offset. e.g. `1990-12-31T23:59:60Z`
-            *offset += 1;
-            return to_datetime(year, month, day, hour, minute, second, nanos, 0);
-        }
-        b'+' => 1,
-        b'-' => -1,
-        _ => return Err(Error::InvalidTimestamp),
-    };
+impl<'a> ParseStructuredData<'a> for Lazy {
+    type Output = RawStructuredData<'a>;
 
-    *offset += 1;
-    if len - *offset < 5 {
-        return Err(Error::InvalidTimestamp);
+    #[inline]
+    fn nil(self, _buf: &'a [u8]) -> Self::Output {
+        RawStructuredData::new(b"")
     }
 
-    let h = convert_2_digits(&buf[*offset..*offset + 2]) as i32;
-    if buf[*offset + 2] != b':' {
-        return Err(Error::InvalidTimestamp);
-    }
-    let m = convert_2_digits(&buf[*offset + 3..*offset + 5]) as i32;
+    #[inline]
+    fn parse(self, buf: &'a [u8], offset: &mut usize) -> Result<Self::Output, Error> {
+        let start = *offset;
+        loop {
+            skip_structured_element(buf, offset)?;
 
-    *offset += 5;
+            if *offset == buf.len() || buf[*offset] == b' ' {
+                break;
+            }
+        }
 
-    to_datetime(
-        year,
-        month,
-        day,
-        hour,
-        minute,
-        second,
-        nanos,
-        sign * (h * 60 * 60 + m * 60),
-    )
+        Ok(RawStructuredData::new(&buf[start..*offset]))
+    }
 }
 
-// SIMD is great but it is might not suitable here. Cause, in our case, the string is short.
+/// Move `offset` right after the `]` of the element, quoted param values
+/// are skipped as a whole, since they might contain `]`.
 #[inline]
-fn take_until_whitespace<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
-    for pos in *offset..buf.len() {
-        if buf[pos] == b' ' {
-            let value = unsafe { std::str::from_utf8_unchecked(&buf[*offset..pos]) };
-            *offset = pos;
-            return Ok(value);
-        }
+pub(crate) fn skip_structured_element(buf: &[u8], offset: &mut usize) -> Result<(), Error> {
+    if buf.get(*offset) != Some(&b'[') {
+        return Err(Error::ExpectedChar('['));
     }
 
-    Err(Error::UnexpectedEndOfInput)
+    let mut pos = *offset + 1;
+    loop {
+        pos = find2(buf, pos, b']', b'"').ok_or(Error::UnexpectedEndOfInput)?;
+        if buf[pos] == b']' {
+            *offset = pos + 1;
+            return Ok(());
+        }
+
+        // skip the quoted value
+        pos = find(buf, pos + 1, b'"').ok_or(Error::UnexpectedEndOfInput)? + 1;
+    }
 }
 
-fn parse_sd_params<'a>(
+fn parse_sd_params<'a, B: Buffers<'a>>(
     buf: &'a [u8],
     offset: &mut usize,
+    buffers: &mut B,
 ) -> Result<Vec<(&'a str, &'a str)>, Error> {
-    let mut params = Vec::with_capacity(4);
+    let mut params = buffers.params();
 
     loop {
         let key = parse_param_key(buf, offset)?;
 
-        if buf[*offset] != b'=' {
+        if byte_at(buf, *offset)? != b'=' {
             return Err(Error::ExpectedChar('='));
         }
         *offset += 1;
@@ -191,7 +166,7 @@ fn parse_sd_params<'a>(
 
         params.push((key, value));
 
-        match buf[*offset] {
+        match byte_at(buf, *offset)? {
             b']' => {
                 *offset += 1;
                 break;
@@ -208,51 +183,42 @@ fn parse_sd_params<'a>(
 }
 
 #[inline]
-fn parse_param_key<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
-    for pos in *offset..buf.len() {
-        let ch = buf[pos];
+pub(crate) fn parse_param_key<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
+    let pos = find2(buf, *offset, b'=', b']').ok_or(Error::UnexpectedEndOfInput)?;
+    let key = unsafe { core::str::from_utf8_unchecked(&buf[*offset..pos]) };
+    *offset = pos;
 
-        if ch == b'=' || ch == b']' {
-            let key = unsafe { std::str::from_utf8_unchecked(&buf[*offset..pos]) };
-            *offset = pos;
-            return Ok(key);
-        }
-    }
-
-    Err(Error::UnexpectedEndOfInput)
+    Ok(key)
 }
 
 #[inline]
-fn parse_param_value<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
-    if buf[*offset] != b'"' {
+pub(crate) fn parse_param_value<'a>(buf: &'a [u8], offset: &mut usize) -> Result<&'a str, Error> {
+    if byte_at(buf, *offset)? != b'"' {
         return Err(Error::ExpectedChar('"'));
     }
     *offset += 1;
 
-    for pos in *offset..buf.len() {
-        if buf[pos] == b'"' {
-            let value = unsafe { std::str::from_utf8_unchecked(&buf[*offset..pos]) };
-            *offset = pos + 1; // 1 for the double quota
-            return Ok(value);
-        }
-    }
+    let pos = find(buf, *offset, b'"').ok_or(Error::UnexpectedEndOfInput)?;
+    let value = unsafe { core::str::from_utf8_unchecked(&buf[*offset..pos]) };
+    *offset = pos + 1; // 1 for the double quota
 
-    Err(Error::UnexpectedEndOfInput)
+    Ok(value)
 }
 
 // example: [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"]
 #[inline]
-fn parse_structured_element<'a>(
+fn parse_structured_element<'a, B: Buffers<'a>>(
     buf: &'a [u8],
     offset: &mut usize,
+    buffers: &mut B,
 ) -> Result<StructuredElement<&'a str>, Error> {
-    if buf[*offset] != b'[' {
+    if byte_at(buf, *offset)? != b'[' {
         return Err(Error::ExpectedChar('['));
     }
     *offset += 1;
 
     // empty structured element, e.g. `[]`
-    if buf[*offset] == b']' {
+    if byte_at(buf, *offset)? == b']' {
         *offset += 1;
         return Ok(StructuredElement {
             id: "",
@@ -260,39 +226,48 @@ fn parse_structured_element<'a>(
         });
     }
 
-    // parse id
-    let mut id = "";
+    let (id, has_params) = parse_sd_id(buf, offset);
+    if !has_params {
+        return Ok(StructuredElement { id, params: vec![] });
+    }
+
+    // parse params
+    let params = parse_sd_params(buf, offset, buffers)?;
+
+    Ok(StructuredElement { id, params })
+}
+
+/// Parse the SD-ID of an element, and tell whether any param follows it.
+#[inline]
+pub(crate) fn parse_sd_id<'a>(buf: &'a [u8], offset: &mut usize) -> (&'a str, bool) {
     for pos in *offset..buf.len() {
         let ch = buf[pos];
         if ch == b' ' {
-            id = unsafe { std::str::from_utf8_unchecked(&buf[*offset..pos]) };
+            let id = unsafe { core::str::from_utf8_unchecked(&buf[*offset..pos]) };
             *offset = pos + 1;
-            break;
+            return (id, true);
         }
 
         if ch == b']' {
             // just id no key-value pairs
-            id = unsafe { std::str::from_utf8_unchecked(&buf[*offset..pos]) };
+            let id = unsafe { core::str::from_utf8_unchecked(&buf[*offset..pos]) };
             *offset = pos + 1;
-            return Ok(StructuredElement { id, params: vec![] });
+            return (id, false);
         }
     }
 
-    // parse params
-    let params = parse_sd_params(buf, offset)?;
-
-    Ok(StructuredElement { id, params })
+    ("", true)
 }
 
-fn parse_structured_data<'a>(
+fn parse_structured_data<'a, B: Buffers<'a>>(
     buf: &'a [u8],
     offset: &mut usize,
+    buffers: &mut B,
 ) -> Result<Vec<StructuredElement<&'a str>>, Error> {
-    // 4 is RawVec::MIN_NON_ZERO_CAP
-    let mut elements = Vec::with_capacity(4);
+    let mut elements = buffers.elements();
 
     loop {
-        let element = parse_structured_element(buf, offset)?;
+        let element = parse_structured_element(buf, offset, buffers)?;
         elements.push(element);
 
         // 1. empty message(aka STRUCTURED-DATA Only),
@@ -305,12 +280,131 @@ fn parse_structured_data<'a>(
     Ok(elements)
 }
 
+// Same as `parse_structured_data`, but only spans are recorded, into `scratch`.
+fn parse_structured_data_in(
+    buf: &[u8],
+    offset: &mut usize,
+    scratch: &mut Scratch,
+) -> Result<(), Error> {
+    loop {
+        if byte_at(buf, *offset)? != b'[' {
+            return Err(Error::ExpectedChar('['));
+        }
+        *offset += 1;
+
+        let params_start = scratch.params.len();
+        let id_start = *offset;
+        let (id, has_params) = if byte_at(buf, *offset)? == b']' {
+            // empty structured element, e.g. `[]`
+            *offset += 1;
+            ("", false)
+        } else {
+            parse_sd_id(buf, offset)
+        };
+        let id = (id_start, id_start + id.len());
+
+        if has_params {
+            loop {
+                let key_start = *offset;
+                parse_param_key(buf, offset)?;
+                let key = (key_start, *offset);
+
+                if byte_at(buf, *offset)? != b'=' {
+                    return Err(Error::ExpectedChar('='));
+                }
+                *offset += 1;
+
+                let value_start = *offset + 1;
+                parse_param_value(buf, offset)?;
+                scratch.params.push((key, (value_start, *offset - 1)));
+
+                match byte_at(buf, *offset)? {
+                    b']' => {
+                        *offset += 1;
+                        break;
+                    }
+                    b' ' => {
+                        *offset += 1;
+                    }
+                    _ch => return Err(Error::InvalidStructuredData),
+                }
+            }
+        }
+
+        scratch
+            .elements
+            .push((id, params_start..scratch.params.len()));
+
+        if *offset == buf.len() || buf[*offset] == b' ' {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
 /// Parse an array of bytes into a `Message` object
 ///
 /// NOTE: `SIMD` is great, but it might not be suitable here, cause our
 /// header part is relatively short, so the performance might not be
-/// as good as we expected.
+/// as good as we expected. The `simd` feature only uses it for long
+/// fields, e.g. big structured data.
 pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
+    parse_message_with(buf, &mut Alloc, false)
+}
+
+/// Parse an array of bytes into a `Message` whose structured data lives in
+/// `scratch`, instead of `Vec`s allocated for every message.
+///
+/// `scratch` keeps its capacity, so once it is big enough for the messages
+/// at hand, parsing does not allocate at all.
+pub fn parse_message_in<'a>(
+    buf: &'a [u8],
+    scratch: &'a mut Scratch,
+) -> Result<Message<&'a str, StructuredDataRef<'a>>, Error> {
+    parse_message_with(buf, scratch, false)
+}
+
+/// Parse an array of bytes into a `Message`, but leave the structured data
+/// unparsed. Its elements are parsed only when iterating them, which is
+/// much cheaper when the structured data is not needed at all.
+pub fn parse_message_lazy(buf: &[u8]) -> Result<Message<&str, RawStructuredData<'_>>, Error> {
+    parse_message_with(buf, Lazy, false)
+}
+
+/// Same as `parse_message`, but the timestamp is converted into `T` instead
+/// of `chrono::DateTime`, e.g. the built-in `Timestamp`, or `time::OffsetDateTime`
+/// with the `time` feature.
+pub fn parse_message_as<T>(
+    buf: &[u8],
+) -> Result<Message<&str, Vec<StructuredElement<&str>>, T>, Error>
+where
+    T: TryFrom<Timestamp>,
+    Error: From<T::Error>,
+{
+    parse_message_with(buf, &mut Alloc, false)
+}
+
+/// Same as `parse_message_as::<Timestamp>`, but the timestamp may also be one
+/// of the non RFC 3339 variants that `timestamp::parse_lenient` accepts.
+/// `Timestamp::has_offset` tells whether the sender specified an offset.
+pub fn parse_message_lenient(
+    buf: &[u8],
+) -> Result<Message<&str, Vec<StructuredElement<&str>>, Timestamp>, Error> {
+    parse_message_with(buf, &mut Alloc, true)
+}
+
+#[inline]
+fn parse_message_with<'a, P, T>(
+    buf: &'a [u8],
+    sd: P,
+    lenient: bool,
+) -> Result<Message<&'a str, P::Output, T>, Error>
+where
+    P: ParseStructuredData<'a>,
+    T: TryFrom<Timestamp>,
+    Error: From<T::Error>,
+{
     let len = buf.len();
 
     // Parse priority
@@ -322,8 +416,7 @@ pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
 
     let mut offset = 1;
     let mut prival = 0i32;
-    for pos in 1..len {
-        let ch = buf[pos];
+    for (pos, &ch) in buf.iter().enumerate().skip(1) {
         if !ch.is_ascii_digit() {
             if ch == b'>' {
                 offset = pos + 1;
@@ -343,7 +436,7 @@ pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
     //
     // https://datatracker.ietf.org/doc/html/rfc5424#section-9.1
     let version = {
-        let ch = buf[offset];
+        let ch = byte_at(buf, offset)?;
         if !ch.is_ascii_digit() {
             return Err(Error::ExpectedChar(ch as char));
         }
@@ -352,49 +445,55 @@ pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
         (ch - b'0') as u32
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
     // Parse timestamp
-    let timestamp = if buf[offset] == b'-' {
+    let timestamp = if byte_at(buf, offset)? == b'-' {
         offset += 1;
         None
     } else {
-        Some(parse_timestamp(buf, &mut offset)?)
+        let timestamp = if lenient {
+            parse_lenient_at(buf, &mut offset)?
+        } else {
+            parse_rfc3339(buf, &mut offset)?
+        };
+
+        Some(T::try_from(timestamp)?)
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
-    let hostname = if buf[offset] == b'-' {
+    let hostname = if byte_at(buf, offset)? == b'-' {
         offset += 1;
         None
     } else {
         Some(take_until_whitespace(buf, &mut offset)?)
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
-    let appname = if buf[offset] == b'-' {
+    let appname = if byte_at(buf, offset)? == b'-' {
         offset += 1;
         None
     } else {
         Some(take_until_whitespace(buf, &mut offset)?)
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
-    let procid = if buf[offset] == b'-' {
+    let procid = if byte_at(buf, offset)? == b'-' {
         offset += 1;
         None
     } else {
@@ -405,36 +504,36 @@ pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
         }
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
-    let msgid = if buf[offset] == b'-' {
+    let msgid = if byte_at(buf, offset)? == b'-' {
         offset += 1;
         None
     } else {
         Some(take_until_whitespace(buf, &mut offset)?)
     };
 
-    if buf[offset] != b' ' {
+    if byte_at(buf, offset)? != b' ' {
         return Err(Error::ExpectSeparator);
     }
     offset += 1;
 
     // structured data
-    let structured_data = if buf[offset] == b'-' {
+    let structured_data = if byte_at(buf, offset)? == b'-' {
         offset += 1;
-        Vec::new()
+        sd.nil(buf)
     } else {
-        parse_structured_data(buf, &mut offset)?
+        sd.parse(buf, &mut offset)?
     };
 
     // message
-    if offset < len && buf[offset] == b' ' {
+    if buf.get(offset) == Some(&b' ') {
         offset += 1;
     }
-    let msg = unsafe { std::str::from_utf8_unchecked(&buf[offset..]) };
+    let msg = unsafe { core::str::from_utf8_unchecked(&buf[offset..]) };
 
     Ok(Message {
         severity,
@@ -450,27 +549,240 @@ pub fn parse_message(buf: &[u8]) -> Result<Message<&str>, Error> {
     })
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Reuse the allocation of a `Vec` for another lifetime of its elements.
+/// Both types must have the same layout, which is checked at compile time.
+#[inline]
+fn recycle<T, U>(mut vec: Vec<T>) -> Vec<U> {
+    const {
+        assert!(core::mem::size_of::<T>() == core::mem::size_of::<U>());
+        assert!(core::mem::align_of::<T>() == core::mem::align_of::<U>());
+    }
 
-    #[test]
-    fn test_convert_2_digits() {
-        for i in 0..99 {
-            let s = format!("{:2}", i);
-            let actual = convert_2_digits(&s.as_bytes()[..2]);
-            assert_eq!(actual, i);
+    vec.clear();
+    let mut vec = core::mem::ManuallyDrop::new(vec);
+    // SAFETY: the buffer was allocated for `capacity` elements with the same
+    // size and alignment, and there are no elements to reinterpret
+    unsafe { Vec::from_raw_parts(vec.as_mut_ptr().cast::<U>(), 0, vec.capacity()) }
+}
+
+/// Parse many messages at once, e.g. the datagrams returned by a single
+/// `recvmmsg` call.
+///
+/// `BatchParser` keeps the structured data `Vec`s of previous batches,
+/// so steady-state parsing does not pay for allocating them again.
+#[derive(Default)]
+pub struct BatchParser {
+    elements: Vec<Vec<StructuredElement<&'static str>>>,
+    params: Vec<Vec<(&'static str, &'static str)>>,
+}
+
+impl<'a> Buffers<'a> for BatchParser {
+    #[inline]
+    fn elements(&mut self) -> Vec<StructuredElement<&'a str>> {
+        match self.elements.pop() {
+            Some(elements) => recycle(elements),
+            None => Alloc.elements(),
         }
     }
 
-    #[test]
-    fn test_convert_4_digits() {
-        for i in 0..9999 {
-            let s = format!("{:4}", i);
-            let actual = convert_4_digits(&s.as_bytes()[..4]);
-            assert_eq!(i, actual);
+    #[inline]
+    fn params(&mut self) -> Vec<(&'a str, &'a str)> {
+        match self.params.pop() {
+            Some(params) => recycle(params),
+            None => Alloc.params(),
+        }
+    }
+}
+
+impl BatchParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse every input and append the results to `out`, in the same order.
+    ///
+    /// Messages already in `out` are dropped first, and their structured
+    /// data buffers are reused for the new ones.
+    pub fn parse_batch<'a>(
+        &mut self,
+        inputs: &[&'a [u8]],
+        out: &mut Vec<Result<Message<&'a str>, Error>>,
+    ) {
+        self.reclaim(out);
+
+        out.reserve(inputs.len());
+        for input in inputs {
+            out.push(parse_message_with(input, &mut *self, false));
+        }
+    }
+
+    /// Drain `out` and keep the structured data buffers of its messages
+    /// for the next batch.
+    pub fn reclaim(&mut self, out: &mut Vec<Result<Message<&str>, Error>>) {
+        for msg in out.drain(..).flatten() {
+            let mut elements = msg.structured_data;
+            if elements.capacity() == 0 {
+                continue;
+            }
+
+            for element in elements.drain(..) {
+                if element.params.capacity() != 0 {
+                    self.params.push(recycle(element.params));
+                }
+            }
+
+            self.elements.push(recycle(elements));
+        }
+    }
+}
+
+/// Parse every input into `out`, a convenience for a single batch.
+///
+/// This creates a new `BatchParser` on every call, so only the buffers of the
+/// messages still in `out` are reused, and anything beyond them is allocated
+/// again. Keep a `BatchParser` around instead to reuse buffers across
+/// batches, even when `out` can not outlive the inputs.
+pub fn parse_batch<'a>(inputs: &[&'a [u8]], out: &mut Vec<Result<Message<&'a str>, Error>>) {
+    BatchParser::new().parse_batch(inputs, out)
+}
+
+/// Append `msg` to `out` in the RFC 5424 format.
+///
+/// The version is taken from `msg.protocol`, and is `1` for messages of other
+/// protocols. Header fields are truncated to their maximum length, characters
+/// other than printable US-ASCII are replaced by `_`, and empty fields are
+/// written as NILVALUE. Timestamps are written with microseconds, those which
+/// can't be represented (e.g. beyond year 9999) as NILVALUE.
+///
+/// Param values are expected to be escaped already, like they are kept by the
+/// parser, so only a `"`, `]` or `\` which doesn't start an escape sequence
+/// is escaped.
+pub fn format_message<S>(msg: &Message<S>, out: &mut Vec<u8>)
+where
+    S: AsRef<str> + Ord + PartialEq + Clone,
+{
+    let version = match msg.protocol {
+        Protocol::RFC5424(version) => version,
+        _ => 1,
+    };
+    push_pri(msg.severity, msg.facility, out);
+    push_number(version as u64, out);
+    out.push(b' ');
+
+    let mut buf = [0u8; crate::timestamp::MAX_LEN];
+    let timestamp = msg
+        .timestamp
+        .as_ref()
+        .and_then(|ts| crate::timestamp::format_rfc3339(&Timestamp::from(*ts), 6, &mut buf).ok());
+    match timestamp {
+        Some(len) => out.extend_from_slice(&buf[..len]),
+        None => out.push(b'-'),
+    }
+
+    out.push(b' ');
+    push_field(msg.hostname.as_ref().map(AsRef::as_ref), 255, out);
+    out.push(b' ');
+    push_field(msg.appname.as_ref().map(AsRef::as_ref), 48, out);
+    out.push(b' ');
+    match &msg.procid {
+        Some(ProcId::PID(pid)) => {
+            if *pid < 0 {
+                out.push(b'-');
+            }
+            push_number(pid.unsigned_abs() as u64, out);
+        }
+        Some(ProcId::Name(name)) => push_field(Some(name.as_ref()), 128, out),
+        None => out.push(b'-'),
+    }
+    out.push(b' ');
+    push_field(msg.msgid.as_ref().map(AsRef::as_ref), 32, out);
+    out.push(b' ');
+
+    if msg.structured_data.is_empty() {
+        out.push(b'-');
+    }
+    for element in &msg.structured_data {
+        out.push(b'[');
+        push_sd_name(element.id.as_ref(), out);
+        for (key, value) in &element.params {
+            out.push(b' ');
+            push_sd_name(key.as_ref(), out);
+            out.extend_from_slice(b"=\"");
+            push_param_value(value.as_ref(), out);
+            out.push(b'"');
+        }
+        out.push(b']');
+    }
+
+    let text = msg.msg.as_ref();
+    if !text.is_empty() {
+        out.push(b' ');
+        out.extend_from_slice(text.as_bytes());
+    }
+}
+
+/// Append `<PRI>`
+pub(crate) fn push_pri(severity: Severity, facility: Facility, out: &mut Vec<u8>) {
+    out.push(b'<');
+    push_number(((facility as u64) << 3) | severity as u64, out);
+    out.push(b'>');
+}
+
+pub(crate) fn push_number(mut n: u64, out: &mut Vec<u8>) {
+    let mut digits = [0u8; 20];
+    let mut pos = digits.len();
+    loop {
+        pos -= 1;
+        digits[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
         }
     }
+    out.extend_from_slice(&digits[pos..]);
+}
+
+/// Append a header field, or NILVALUE if it is `None` or empty.
+pub(crate) fn push_field(value: Option<&str>, max_len: usize, out: &mut Vec<u8>) {
+    match value.filter(|v| !v.is_empty()) {
+        Some(value) => out.extend(value.chars().take(max_len).map(|ch| match ch {
+            '!'..='~' => ch as u8,
+            _ => b'_',
+        })),
+        None => out.push(b'-'),
+    }
+}
+
+/// Append an SD-ID or PARAM-NAME, which are at most 32 characters long, and
+/// can't contain `=`, ` `, `]` and `"`.
+fn push_sd_name(name: &str, out: &mut Vec<u8>) {
+    out.extend(name.chars().take(32).map(|ch| match ch {
+        '=' | ']' | '"' => b'_',
+        '!'..='~' => ch as u8,
+        _ => b'_',
+    }));
+}
+
+fn push_param_value(value: &str, out: &mut Vec<u8>) {
+    let bytes = value.as_bytes();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if matches!(bytes.get(pos + 1), Some(b'"' | b'\\' | b']')) => {
+                out.extend_from_slice(&bytes[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            ch @ (b'"' | b'\\' | b']') => out.extend_from_slice(&[b'\\', ch]),
+            ch => out.push(ch),
+        }
+        pos += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
     #[test]
     fn rfc5424_examples() {
@@ -496,25 +808,275 @@ mod tests {
             "1990-12-31T15:59:59-08:00",
             "1937-01-01T12:00:27.87+00:20",
         ] {
-            let ref mut offset = 0;
+            let offset = &mut 0;
             let got = parse_timestamp(input.as_bytes(), offset).unwrap();
             let want = chrono::DateTime::parse_from_rfc3339(input).unwrap();
             assert_eq!(got, want, "input: {input}, want: {}", want.to_rfc3339())
         }
     }
 
+    #[test]
+    fn timestamp_edge_cases() {
+        for (input, want) in [
+            // leap second
+            ("1990-12-31T23:59:60Z", "1990-12-31T23:59:60Z"),
+            ("1990-12-31T15:59:60.5-08:00", "1990-12-31T15:59:60.5-08:00"),
+            // lowercase separators
+            ("1996-12-19t16:39:57-08:00", "1996-12-19T16:39:57-08:00"),
+            ("1990-12-31t23:59:59z", "1990-12-31T23:59:59Z"),
+            // more than 9 fractional digits
+            (
+                "1985-04-12T23:20:50.123456789123Z",
+                "1985-04-12T23:20:50.123456789Z",
+            ),
+            (
+                "1985-04-12T23:20:50.1234567891+01:00",
+                "1985-04-12T23:20:50.123456789+01:00",
+            ),
+            // unknown local offset
+            ("1985-04-12T23:20:50.52-00:00", "1985-04-12T23:20:50.52Z"),
+        ] {
+            let offset = &mut 0;
+            let got = parse_timestamp(input.as_bytes(), offset).unwrap();
+            let want = DateTime::parse_from_rfc3339(want).unwrap();
+            assert_eq!(got, want, "input: {input}");
+            assert_eq!(got.offset(), want.offset(), "input: {input}");
+            assert_eq!(*offset, input.len(), "input: {input}");
+        }
+
+        let leap = parse_timestamp(b"1990-12-31T23:59:60Z", &mut 0).unwrap();
+        assert_eq!(leap.timestamp_subsec_nanos(), 1_000_000_000);
+    }
+
+    #[test]
+    fn timestamp_without_fraction() {
+        let msg = parse_message(b"<34>1 2019-02-13T19:48:34Z host su - - - hello").unwrap();
+        assert_eq!(
+            msg.timestamp,
+            Some(chrono::DateTime::parse_from_rfc3339("2019-02-13T19:48:34Z").unwrap())
+        );
+        assert_eq!(msg.hostname, Some("host"));
+        assert_eq!(msg.msg, "hello");
+    }
+
+    #[test]
+    fn lenient_timestamp() {
+        let input = b"<34>1 2023-04-07 12:52:00 mymachine su - ID47 - hello";
+        assert_eq!(parse_message(input), Err(Error::InvalidTimestamp));
+
+        let msg = parse_message_lenient(input).unwrap();
+        let timestamp = msg.timestamp.unwrap();
+        assert!(!timestamp.has_offset);
+        assert_eq!((timestamp.hour, timestamp.minute), (12, 52));
+        assert_eq!(msg.hostname, Some("mymachine"));
+        assert_eq!(msg.msg, "hello");
+
+        let msg = parse_message_lenient(b"<34>1 1680871920 mymachine su - ID47 - hello").unwrap();
+        assert_eq!(
+            DateTime::try_from(msg.timestamp.unwrap()),
+            Ok(DateTime::parse_from_rfc3339("2023-04-07T12:52:00Z").unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_as_timestamp() {
+        let input = b"<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - hello";
+
+        let msg = parse_message_as::<Timestamp>(input).unwrap();
+        assert_eq!(
+            msg.timestamp,
+            Some(Timestamp {
+                year: 2003,
+                month: 8,
+                day: 24,
+                hour: 5,
+                minute: 14,
+                second: 15,
+                nanos: 3000,
+                offset: -7 * 3600,
+                has_offset: true,
+            })
+        );
+        assert_eq!(msg.msg, "hello");
+
+        let msg = parse_message_as::<DateTime<FixedOffset>>(input).unwrap();
+        assert_eq!(msg, parse_message(input).unwrap());
+    }
+
     #[test]
     fn multiple_structured_data() {
         let input = b"[exampleSDID@32473 iut=\"3\" eventSource=\"Application\"][examplePriority@32473 class=\"high\"] BOMAn application event log entry...";
 
-        let elements = parse_structured_data(input, &mut 0).unwrap();
+        let elements = parse_structured_data(input, &mut 0, &mut Alloc).unwrap();
         assert_eq!(elements.len(), 2);
     }
 
+    #[test]
+    fn batch_reuses_buffers() {
+        let input = b"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut=\"3\"][examplePriority@32473 class=\"high\"] BOMAn application event log entry...";
+        // more elements and params than a fresh `Vec` has room for
+        let large = br#"<165>1 - - - - - [a x="1" y="2" z="3" w="4" v="5"][b][c][d][e]"#;
+
+        let mut parser = BatchParser::new();
+        let mut out = vec![];
+        parser.parse_batch(&[large, b"garbage"], &mut out);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0], parse_message(large));
+        assert!(out[1].is_err());
+
+        let capacity = out[0].as_ref().unwrap().structured_data.capacity();
+        assert!(capacity > Alloc.elements().capacity());
+        parser.reclaim(&mut out);
+        assert_eq!(parser.elements.len(), 1);
+        assert_eq!(parser.params.len(), 1);
+
+        parser.parse_batch(&[input], &mut out);
+        assert_eq!(out[0], parse_message(input));
+        let structured_data = &out[0].as_ref().unwrap().structured_data;
+        assert_eq!(structured_data.capacity(), capacity);
+        assert!(structured_data
+            .iter()
+            .any(|element| element.params.capacity() > 4));
+        assert!(parser.elements.is_empty());
+        assert!(parser.params.is_empty());
+    }
+
+    #[test]
+    fn parse_into_scratch() {
+        let mut scratch = Scratch::new();
+
+        for input in [
+            r##"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - BOM'su root' failed for lonvick on /dev/pts/8"##,
+            r##"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [empty][] qwerty"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [non_empty x="1"][empty] qwerty"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [empty not_really=""]"##,
+        ] {
+            let want = parse_message(input.as_bytes()).unwrap();
+            let got = parse_message_in(input.as_bytes(), &mut scratch).unwrap();
+
+            assert_eq!(
+                got.structured_data.to_vec(),
+                want.structured_data,
+                "{input}"
+            );
+            assert_eq!(got.msg, want.msg);
+            assert_eq!(got.hostname, want.hostname);
+        }
+
+        let input = br#"<165>1 - - - - - [exampleSDID@32473 iut="3" eventSource="Application"]"#;
+        let msg = parse_message_in(input, &mut scratch).unwrap();
+        let element = msg.structured_data.get("exampleSDID@32473").unwrap();
+        assert_eq!(element.get("eventSource"), Some("Application"));
+        assert_eq!(element.get("eventID"), None);
+    }
+
+    #[test]
+    fn lazy_structured_data() {
+        for input in [
+            r##"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - BOM'su root' failed for lonvick on /dev/pts/8"##,
+            r##"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [empty][] qwerty"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [non_empty x="1"][empty] qwerty"##,
+            r##"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [quoted x="[a b]"] qwerty"##,
+        ] {
+            let want = parse_message(input.as_bytes()).unwrap();
+            let got = parse_message_lazy(input.as_bytes()).unwrap();
+
+            assert_eq!(
+                got.structured_data.parse().unwrap(),
+                want.structured_data,
+                "{input}"
+            );
+            assert_eq!(got.msg, want.msg);
+        }
+
+        let msg = parse_message_lazy(br#"<13>1 - - - - - [a x="1"][b y=2] qwerty"#).unwrap();
+        assert_eq!(msg.structured_data.as_str(), r#"[a x="1"][b y=2]"#);
+        assert_eq!(msg.msg, "qwerty");
+
+        let mut elements = msg.structured_data.elements();
+        let a = elements.next().unwrap().unwrap();
+        assert_eq!(a.id, "a");
+        assert_eq!(a.params().collect::<Vec<_>>(), [Ok(("x", "1"))]);
+        let b = elements.next().unwrap().unwrap();
+        assert_eq!(b.id, "b");
+        assert_eq!(
+            b.params().collect::<Vec<_>>(),
+            [Err(Error::ExpectedChar('"'))]
+        );
+        assert!(elements.next().is_none());
+
+        assert_eq!(
+            parse_message_lazy(br#"<13>1 - - - - - [a x="1" qwerty"#).unwrap_err(),
+            Error::UnexpectedEndOfInput
+        );
+    }
+
+    #[test]
+    fn format_roundtrip() {
+        for input in [
+            r##"<34>1 2003-10-11T22:14:15.003000Z mymachine.example.com su - ID47 - 'su root' failed for lonvick on /dev/pts/8"##,
+            r##"<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts."##,
+            r##"<165>1 2003-10-11T22:14:15.003000Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"]"##,
+            r#"<13>1 - - - 12 - [a path="C:\\temp\]"]"#,
+        ] {
+            let mut out = Vec::new();
+            format_message(&parse_message(input.as_bytes()).unwrap(), &mut out);
+            assert_eq!(core::str::from_utf8(&out).unwrap(), input);
+        }
+    }
+
+    #[test]
+    fn format_sanitizes() {
+        let msg = Message {
+            severity: Severity::ERR,
+            facility: Facility::LOCAL7,
+            protocol: Protocol::Kmsg,
+            timestamp: None,
+            hostname: Some("my host"),
+            appname: Some("ünïcode"),
+            procid: Some(ProcId::Name("")),
+            msgid: Some(""),
+            structured_data: vec![StructuredElement {
+                id: "a b",
+                params: vec![("k=", r#"say "hi" [1] C:\\temp \x"#)],
+            }],
+            msg: "",
+        };
+
+        let mut out = Vec::new();
+        format_message(&msg, &mut out);
+        assert_eq!(
+            core::str::from_utf8(&out).unwrap(),
+            r#"<187>1 - my_host _n_code - - [a_b k_="say \"hi\" [1\] C:\\temp \\x"]"#
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let input = br#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog 8710 ID47 [exampleSDID@32473 iut="3"][b] msg"#;
+        for end in 0..input.len() {
+            // a message may end right after any element, but never panics
+            let _ = parse_message(&input[..end]);
+            let _ = parse_message_in(&input[..end], &mut Scratch::new());
+            let _ = parse_message_lazy(&input[..end]);
+        }
+
+        for input in [
+            &b"<13>1"[..],
+            b"<13>1 -",
+            b"<13>1 - - - - -",
+            br#"<13>1 - - - - - [a x="#,
+        ] {
+            assert_eq!(parse_message(input), Err(Error::UnexpectedEndOfInput));
+        }
+    }
+
     #[test]
     fn empty_structured_data() {
-        for input in ["[] "] {
-            let _ = parse_structured_data(input.as_bytes(), &mut 0).unwrap();
+        for input in ["[] ", "[]"] {
+            let _ = parse_structured_data(input.as_bytes(), &mut 0, &mut Alloc).unwrap();
         }
     }
 }
@@ -0,0 +1,78 @@
+//! Delimiter scanning for the parsers.
+//!
+//! Most header fields are short, so a plain loop wins there. With the `simd`
+//! feature, the first `SHORT_FIELD` bytes are still checked one by one, and
+//! only longer fields, e.g. hostnames of cloud platforms or big SD blocks,
+//! are handed over to `memchr`.
+
+#[cfg(feature = "simd")]
+const SHORT_FIELD: usize = 16;
+
+/// Find the position of the first `needle` in `buf[start..]`
+#[inline]
+pub(crate) fn find(buf: &[u8], start: usize, needle: u8) -> Option<usize> {
+    #[cfg(feature = "simd")]
+    {
+        let short = buf.len().min(start + SHORT_FIELD);
+        if let Some(pos) = buf.get(start..short)?.iter().position(|ch| *ch == needle) {
+            return Some(start + pos);
+        }
+
+        memchr::memchr(needle, &buf[short..]).map(|pos| short + pos)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    {
+        buf.get(start..)?
+            .iter()
+            .position(|ch| *ch == needle)
+            .map(|pos| start + pos)
+    }
+}
+
+/// Find the position of the first `n1` or `n2` in `buf[start..]`
+#[inline]
+pub(crate) fn find2(buf: &[u8], start: usize, n1: u8, n2: u8) -> Option<usize> {
+    #[cfg(feature = "simd")]
+    {
+        let short = buf.len().min(start + SHORT_FIELD);
+        let found = buf
+            .get(start..short)?
+            .iter()
+            .position(|ch| *ch == n1 || *ch == n2);
+        if let Some(pos) = found {
+            return Some(start + pos);
+        }
+
+        memchr::memchr2(n1, n2, &buf[short..]).map(|pos| short + pos)
+    }
+
+    #[cfg(not(feature = "simd"))]
+    {
+        buf.get(start..)?
+            .iter()
+            .position(|ch| *ch == n1 || *ch == n2)
+            .map(|pos| start + pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_and_long_fields() {
+        let long = format!("{}=\"{}\"]", "k".repeat(40), "v".repeat(100));
+        let buf = long.as_bytes();
+
+        assert_eq!(find(buf, 0, b'='), Some(40));
+        assert_eq!(find(buf, 0, b'"'), Some(41));
+        assert_eq!(find(buf, 42, b'"'), Some(142));
+        assert_eq!(find(buf, 0, b' '), None);
+        assert_eq!(find(buf, buf.len(), b']'), None);
+        assert_eq!(find2(buf, 0, b']', b'='), Some(40));
+        assert_eq!(find2(buf, 41, b']', b'='), Some(143));
+        assert_eq!(find2(buf, 3, b'k', b'v'), Some(3));
+        assert_eq!(find2(b"kkk", 0, b'=', b']'), None);
+    }
+}
@@ -0,0 +1,469 @@
+//! Send syslog messages over UDP (RFC 5426), TCP (RFC 6587), TLS (RFC 5425,
+//! with the `tls` feature) or Unix sockets, e.g. `/dev/log`.
+//!
+//! ```no_run
+//! use syslog::sender::{Format, Sender};
+//! use syslog::{Facility, Message, ProcId, Protocol, Severity};
+//!
+//! let mut sender = Sender::tcp("logs.example.com:601").unwrap();
+//! let mut local = Sender::unix("/dev/log").unwrap().format(Format::Rfc3164);
+//!
+//! let msg = Message {
+//!     severity: Severity::INFO,
+//!     facility: Facility::DAEMON,
+//!     protocol: Protocol::RFC5424(1),
+//!     timestamp: Some(chrono::DateTime::<chrono::Utc>::from(std::time::SystemTime::now()).fixed_offset()),
+//!     hostname: Some("web01"),
+//!     appname: Some("app"),
+//!     procid: Some(ProcId::PID(std::process::id() as i32)),
+//!     msgid: None,
+//!     structured_data: vec![],
+//!     msg: "request done",
+//! };
+//! sender.send(&msg).unwrap();
+//! local.send(&msg).unwrap();
+//! ```
+
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::{UnixDatagram, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "tls")]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "tls")]
+use rustls::pki_types::ServerName;
+#[cfg(feature = "tls")]
+use rustls::{ClientConfig, ClientConnection, StreamOwned};
+
+#[cfg(feature = "tls")]
+use crate::tls;
+use crate::{rfc3164, rfc5424, Message};
+
+/// The size of datagrams every IPv4 receiver must accept, RFC 5426 section 3.2.
+pub const UDP_MIN_LEN_IPV4: usize = 480;
+/// The size of datagrams every IPv6 receiver must accept.
+pub const UDP_MIN_LEN_IPV6: usize = 1180;
+/// The size of datagrams receivers should accept, and the default `max_len`
+/// of UDP senders.
+pub const UDP_MAX_LEN: usize = 2048;
+
+/// The default write timeout of stream transports.
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The format messages are serialized in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// See `rfc5424::format_message`
+    #[default]
+    Rfc5424,
+    /// See `rfc3164::format_message`
+    Rfc3164,
+}
+
+/// How messages are delimited on stream transports, see RFC 6587.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// `MSG-LEN SP SYSLOG-MSG`, the default for TCP
+    #[default]
+    OctetCounting,
+    /// Every message is terminated by `\n`, newlines in the message itself
+    /// are replaced by spaces. The default for Unix stream sockets.
+    NonTransparent,
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    Tcp {
+        addrs: Vec<SocketAddr>,
+        stream: Option<TcpStream>,
+    },
+    #[cfg(feature = "tls")]
+    Tls {
+        addrs: Vec<SocketAddr>,
+        server_name: ServerName<'static>,
+        config: Arc<ClientConfig>,
+        stream: Option<Box<StreamOwned<ClientConnection, TcpStream>>>,
+    },
+    #[cfg(unix)]
+    UnixDatagram {
+        path: PathBuf,
+        socket: UnixDatagram,
+    },
+    #[cfg(unix)]
+    UnixStream {
+        path: PathBuf,
+        stream: Option<UnixStream>,
+    },
+}
+
+impl Transport {
+    /// Set the write timeout of the current connection, which is dropped if
+    /// that fails, so the next send reconnects and reports the error.
+    fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        let result = match self {
+            Transport::Tcp {
+                stream: Some(stream),
+                ..
+            } => stream.set_write_timeout(timeout),
+            #[cfg(feature = "tls")]
+            Transport::Tls {
+                stream: Some(stream),
+                ..
+            } => stream.sock.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Transport::UnixStream {
+                stream: Some(stream),
+                ..
+            } => stream.set_write_timeout(timeout),
+            _ => return,
+        };
+
+        if result.is_err() {
+            match self {
+                Transport::Tcp { stream, .. } => *stream = None,
+                #[cfg(feature = "tls")]
+                Transport::Tls { stream, .. } => *stream = None,
+                #[cfg(unix)]
+                Transport::UnixStream { stream, .. } => *stream = None,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Serializes messages and sends them to a syslog server.
+///
+/// Stream transports reconnect when a write fails or times out, and retry the
+/// message once on the new connection. The message may be delivered twice if
+/// the failed write was partially received.
+pub struct Sender {
+    transport: Transport,
+    format: Format,
+    framing: Framing,
+    max_len: usize,
+    write_timeout: Option<Duration>,
+    buf: Vec<u8>,
+    frame: Vec<u8>,
+}
+
+impl Sender {
+    /// Send datagrams to `addr`, from an ephemeral port. Messages longer than
+    /// `UDP_MAX_LEN` are truncated.
+    pub fn udp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0u16; 8], 0).into(),
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+
+        Ok(Self::new(
+            Transport::Udp(socket),
+            Framing::OctetCounting,
+            UDP_MAX_LEN,
+        ))
+    }
+
+    /// Connect to `addr` over TCP, with octet-counting framing.
+    pub fn tcp<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
+        let stream = TcpStream::connect(&addrs[..])?;
+
+        Ok(Self::new(
+            Transport::Tcp {
+                addrs,
+                stream: Some(stream),
+            },
+            Framing::OctetCounting,
+            usize::MAX,
+        ))
+    }
+
+    /// Connect to `addr` over TLS, usually on port `tls::TLS_PORT`, with
+    /// octet-counting framing. The server's certificate is authenticated
+    /// as configured by `connector`, and must be valid for `server_name`
+    /// unless it matches a fingerprint.
+    #[cfg(feature = "tls")]
+    pub fn tls<A: ToSocketAddrs>(
+        addr: A,
+        server_name: &str,
+        connector: &tls::Connector,
+    ) -> io::Result<Self> {
+        let addrs = addr.to_socket_addrs()?.collect::<Vec<_>>();
+        let server_name = ServerName::try_from(server_name.to_owned())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let config = connector.config()?;
+        let stream = tls::connect(&addrs, &server_name, &config)?;
+
+        Ok(Self::new(
+            Transport::Tls {
+                addrs,
+                server_name,
+                config,
+                stream: Some(Box::new(stream)),
+            },
+            Framing::OctetCounting,
+            usize::MAX,
+        ))
+    }
+
+    /// Connect to a local syslog socket like `/dev/log`, which is a datagram
+    /// socket on most systems, and a stream socket on some.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::unix_datagram(&path).or_else(|_| Self::unix_stream(&path))
+    }
+
+    #[cfg(unix)]
+    pub fn unix_datagram<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(&path)?;
+
+        Ok(Self::new(
+            Transport::UnixDatagram { path, socket },
+            Framing::OctetCounting,
+            usize::MAX,
+        ))
+    }
+
+    /// Connect to a Unix stream socket, with non-transparent framing.
+    #[cfg(unix)]
+    pub fn unix_stream<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let stream = UnixStream::connect(&path)?;
+
+        Ok(Self::new(
+            Transport::UnixStream {
+                path,
+                stream: Some(stream),
+            },
+            Framing::NonTransparent,
+            usize::MAX,
+        ))
+    }
+
+    fn new(transport: Transport, framing: Framing, max_len: usize) -> Self {
+        Sender {
+            transport,
+            format: Format::default(),
+            framing,
+            max_len,
+            write_timeout: None,
+            buf: Vec::with_capacity(1024),
+            frame: Vec::new(),
+        }
+        .write_timeout(Some(DEFAULT_WRITE_TIMEOUT))
+    }
+
+    /// The format messages are serialized in, RFC 5424 by default.
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The framing of stream transports, datagram transports ignore it.
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Truncate serialized messages to `max_len` bytes, at a UTF-8 character
+    /// boundary. UDP defaults to `UDP_MAX_LEN`, use `UDP_MIN_LEN_IPV4` when
+    /// the receivers or the path MTU are unknown. Other transports don't
+    /// truncate by default.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// How long a write to a stream transport may block, e.g. while the
+    /// server is stalled, before the connection is dropped and the message
+    /// retried on a new one. `DEFAULT_WRITE_TIMEOUT` by default, `None`
+    /// blocks forever.
+    pub fn write_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.write_timeout = timeout;
+        self.transport.set_write_timeout(timeout);
+        self
+    }
+
+    /// Serialize and send `msg`.
+    pub fn send<S>(&mut self, msg: &Message<S>) -> io::Result<()>
+    where
+        S: AsRef<str> + Ord + PartialEq + Clone,
+    {
+        self.buf.clear();
+        match self.format {
+            Format::Rfc5424 => rfc5424::format_message(msg, &mut self.buf),
+            Format::Rfc3164 => rfc3164::format_message(msg, &mut self.buf),
+        }
+        truncate(&mut self.buf, self.max_len);
+
+        let timeout = self.write_timeout;
+        match &mut self.transport {
+            Transport::Udp(socket) => socket.send(&self.buf).map(drop),
+            Transport::Tcp { addrs, stream } => {
+                let frame = frame(&self.buf, self.framing, &mut self.frame);
+                write_or_reconnect(stream, frame, || {
+                    let stream = TcpStream::connect(&addrs[..])?;
+                    stream.set_write_timeout(timeout)?;
+                    Ok(stream)
+                })
+            }
+            #[cfg(feature = "tls")]
+            Transport::Tls {
+                addrs,
+                server_name,
+                config,
+                stream,
+            } => {
+                let frame = frame(&self.buf, self.framing, &mut self.frame);
+                write_or_reconnect(stream, frame, || {
+                    let stream = tls::connect(addrs, server_name, config)?;
+                    stream.sock.set_write_timeout(timeout)?;
+                    Ok(Box::new(stream))
+                })
+            }
+            #[cfg(unix)]
+            Transport::UnixDatagram { path, socket } => match socket.send(&self.buf) {
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::ConnectionRefused | io::ErrorKind::NotConnected
+                    ) =>
+                {
+                    // the syslog daemon restarted, and created a new socket
+                    let new_socket = UnixDatagram::unbound()?;
+                    new_socket.connect(&*path)?;
+                    *socket = new_socket;
+                    socket.send(&self.buf).map(drop)
+                }
+                result => result.map(drop),
+            },
+            #[cfg(unix)]
+            Transport::UnixStream { path, stream } => {
+                let frame = frame(&self.buf, self.framing, &mut self.frame);
+                write_or_reconnect(stream, frame, || {
+                    let stream = UnixStream::connect(&*path)?;
+                    stream.set_write_timeout(timeout)?;
+                    Ok(stream)
+                })
+            }
+        }
+    }
+}
+
+/// Closes TLS connections with a `close_notify` alert, as RFC 5425 requires.
+#[cfg(feature = "tls")]
+impl Drop for Sender {
+    fn drop(&mut self) {
+        if let Transport::Tls {
+            stream: Some(stream),
+            ..
+        } = &mut self.transport
+        {
+            stream.conn.send_close_notify();
+            let _ = stream.flush();
+        }
+    }
+}
+
+/// Truncate `buf` to at most `max_len` bytes, without splitting a UTF-8
+/// character.
+fn truncate(buf: &mut Vec<u8>, max_len: usize) {
+    if buf.len() <= max_len {
+        return;
+    }
+
+    let mut len = max_len;
+    while len > 0 && buf[len] & 0xc0 == 0x80 {
+        len -= 1;
+    }
+    buf.truncate(len);
+}
+
+fn frame<'a>(msg: &'a [u8], framing: Framing, frame: &'a mut Vec<u8>) -> &'a [u8] {
+    frame.clear();
+    match framing {
+        Framing::OctetCounting => {
+            rfc5424::push_number(msg.len() as u64, frame);
+            frame.push(b' ');
+            frame.extend_from_slice(msg);
+        }
+        Framing::NonTransparent => {
+            frame.extend(msg.iter().map(|&ch| if ch == b'\n' { b' ' } else { ch }));
+            frame.push(b'\n');
+        }
+    }
+
+    frame
+}
+
+/// Write `frame` to `stream`, connect again and retry once if there is no
+/// connection or the write fails.
+fn write_or_reconnect<W, C>(stream: &mut Option<W>, frame: &[u8], connect: C) -> io::Result<()>
+where
+    W: Write,
+    C: Fn() -> io::Result<W>,
+{
+    if let Some(conn) = stream {
+        match conn.write_all(frame).and_then(|()| conn.flush()) {
+            Ok(()) => return Ok(()),
+            Err(_) => *stream = None,
+        }
+    }
+
+    let mut conn = connect()?;
+    conn.write_all(frame)?;
+    conn.flush()?;
+    *stream = Some(conn);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncation() {
+        for (input, max_len, want) in [
+            ("hello", 10, "hello"),
+            ("hello", 5, "hello"),
+            ("hello", 3, "hel"),
+            ("hé", 2, "h"),
+            ("hé", 3, "hé"),
+            ("€", 2, ""),
+        ] {
+            let mut buf = input.as_bytes().to_vec();
+            truncate(&mut buf, max_len);
+            assert_eq!(buf, want.as_bytes(), "{input} {max_len}");
+        }
+    }
+
+    #[test]
+    fn framing() {
+        let mut buf = Vec::new();
+        assert_eq!(
+            frame(b"<14>1 - - - - - - hi", Framing::OctetCounting, &mut buf),
+            b"20 <14>1 - - - - - - hi"
+        );
+        assert_eq!(
+            frame(b"<14>hi", Framing::NonTransparent, &mut buf),
+            b"<14>hi\n"
+        );
+        assert_eq!(
+            frame(b"<14>multi\nline\n", Framing::NonTransparent, &mut buf),
+            b"<14>multi line \n"
+        );
+    }
+}
@@ -0,0 +1,639 @@
+//! Receive syslog messages over UDP, TCP, TLS (with the `tls` feature) or
+//! Unix sockets.
+//!
+//! Every listener runs on its own thread, and stream listeners spawn one more
+//! for each connection, up to `Server::max_connections`. Messages are handed
+//! to a `Handler`, e.g. a channel, together with the peer they came from.
+//!
+//! ```
+//! use std::sync::mpsc;
+//! use syslog::server::{Peer, Server};
+//!
+//! let (tx, rx) = mpsc::channel();
+//! let server = Server::new(tx).auto_detect(true);
+//! let listener = server.udp("127.0.0.1:0").unwrap();
+//!
+//! let socket = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+//! let raw = b"<34>Oct 11 22:14:15 mymachine su: 'su root' failed";
+//! socket.send_to(raw, listener.local_addr().unwrap()).unwrap();
+//!
+//! let received = rx.recv().unwrap();
+//! assert_eq!(received.message.hostname.as_deref(), Some("mymachine"));
+//! assert_eq!(received.peer, Peer::Udp(socket.local_addr().unwrap()));
+//!
+//! // stops receiving, and waits for the listener's thread
+//! listener.shutdown();
+//! ```
+
+use std::io::{self, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::{UnixDatagram, UnixListener, UnixStream};
+#[cfg(unix)]
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use chrono::{DateTime, FixedOffset};
+#[cfg(feature = "tls")]
+use rustls::{ServerConnection, StreamOwned};
+
+use crate::framing::Frames;
+use crate::rfc3164::{self, Resolver};
+#[cfg(feature = "tls")]
+use crate::tls;
+use crate::{rfc5424, Error, Message};
+
+/// The default maximum length of a message, which is what RFC 5425 requires
+/// receivers to support.
+pub const DEFAULT_MAX_LEN: usize = 8192;
+
+/// The default maximum number of open connections per stream listener.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 1024;
+
+/// How often blocked reads wake up to check for a shutdown.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A message and where it came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Received {
+    pub message: Message<String>,
+    pub peer: Peer,
+}
+
+/// The sender of a message, the variants depend on the platform and the
+/// enabled features.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Peer {
+    Udp(SocketAddr),
+    Tcp(SocketAddr),
+    /// The subject of the certificate the client authenticated with, e.g.
+    /// `CN=web01, O=Example`, if the listener authenticates clients.
+    #[cfg(feature = "tls")]
+    Tls {
+        addr: SocketAddr,
+        subject: Option<String>,
+    },
+    /// The path the peer's socket is bound to, usually `None`, since local
+    /// clients rarely bind theirs.
+    #[cfg(unix)]
+    Unix(Option<PathBuf>),
+}
+
+/// Receives the messages of a `Server`.
+///
+/// It is called from the threads of all listeners and connections at the
+/// same time.
+pub trait Handler: Send + Sync + 'static {
+    fn handle(&self, received: Received);
+
+    /// Called for frames which can't be parsed, and for framing errors which
+    /// close the connection. They are ignored by default.
+    fn error(&self, peer: &Peer, err: Error) {
+        let _ = (peer, err);
+    }
+}
+
+/// Messages are dropped once the receiver is gone.
+impl Handler for mpsc::Sender<Received> {
+    fn handle(&self, received: Received) {
+        let _ = self.send(received);
+    }
+}
+
+/// Blocks while the channel is full, which applies backpressure to stream
+/// peers.
+impl Handler for mpsc::SyncSender<Received> {
+    fn handle(&self, received: Received) {
+        let _ = self.send(received);
+    }
+}
+
+impl<F> Handler for F
+where
+    F: Fn(Received) + Send + Sync + 'static,
+{
+    fn handle(&self, received: Received) {
+        self(received)
+    }
+}
+
+/// The shared configuration of all listeners of a `Server`.
+struct Config<H> {
+    handler: H,
+    max_len: usize,
+    max_connections: usize,
+    idle_timeout: Option<Duration>,
+    auto_detect: bool,
+    resolver: Resolver<FixedOffset>,
+}
+
+impl<H: Handler> Config<H> {
+    /// Parse and deliver a single frame.
+    fn deliver(&self, frame: &[u8], peer: &Peer) {
+        match self.parse(frame) {
+            Ok(message) => self.handler.handle(Received {
+                message,
+                peer: peer.clone(),
+            }),
+            Err(err) => self.handler.error(peer, err),
+        }
+    }
+
+    fn parse(&self, frame: &[u8]) -> Result<Message<String>, Error> {
+        core::str::from_utf8(frame).map_err(|_| Error::InvalidUtf8)?;
+
+        let msg = if self.auto_detect && !is_rfc5424(frame) {
+            let now = DateTime::from(SystemTime::now());
+            rfc3164::parse_message(frame, &self.resolver, now)?
+        } else {
+            rfc5424::parse_message(frame)?
+        };
+
+        Ok(msg.into_owned())
+    }
+}
+
+/// Whether the PRI is followed by a version, as in `<34>1 `.
+fn is_rfc5424(frame: &[u8]) -> bool {
+    let Some(end) = frame.iter().position(|ch| *ch == b'>') else {
+        return false;
+    };
+
+    matches!(frame.get(end + 1), Some(b'1'..=b'9')) && frame.get(end + 2) == Some(&b' ')
+}
+
+/// Binds listeners which deliver the messages to a `Handler`.
+pub struct Server<H> {
+    config: Arc<Config<H>>,
+}
+
+impl<H: Handler> Server<H> {
+    pub fn new(handler: H) -> Self {
+        Server {
+            config: Arc::new(Config {
+                handler,
+                max_len: DEFAULT_MAX_LEN,
+                max_connections: DEFAULT_MAX_CONNECTIONS,
+                idle_timeout: None,
+                auto_detect: false,
+                resolver: Resolver::new(FixedOffset::east_opt(0).unwrap()),
+            }),
+        }
+    }
+
+    fn config_mut(&mut self) -> &mut Config<H> {
+        Arc::get_mut(&mut self.config).expect("configured before binding listeners")
+    }
+
+    /// The maximum length of a message, `DEFAULT_MAX_LEN` by default. Longer
+    /// datagrams are dropped, and stream connections are closed, since a
+    /// frame with a larger octet count is read as a line, and the rest of the
+    /// connection can't be trusted. Both are reported as
+    /// `Error::MessageTooLong`.
+    ///
+    /// # Panics
+    ///
+    /// If any listener was bound already, like the other options.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.config_mut().max_len = max_len;
+        self
+    }
+
+    /// The maximum number of open connections of each stream listener,
+    /// `DEFAULT_MAX_CONNECTIONS` by default. Connections beyond it are
+    /// closed right away.
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.config_mut().max_connections = max_connections;
+        self
+    }
+
+    /// Close stream connections which haven't sent a complete frame for
+    /// `timeout`. Connections are kept open by default, but TLS handshakes
+    /// time out after `tls::HANDSHAKE_TIMEOUT` at the latest.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.config_mut().idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Parse messages without a version after PRI as RFC 3164, instead of
+    /// rejecting them, see `rfc3164::parse_message`.
+    pub fn auto_detect(mut self, auto_detect: bool) -> Self {
+        self.config_mut().auto_detect = auto_detect;
+        self
+    }
+
+    /// The timezone of RFC 3164 timestamps, UTC by default.
+    pub fn timezone(mut self, timezone: FixedOffset) -> Self {
+        self.config_mut().resolver = Resolver::new(timezone);
+        self
+    }
+
+    /// Receive datagrams on `addr`.
+    pub fn udp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<Listener> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+        let local_addr = socket.local_addr()?;
+
+        self.spawn(
+            "syslog-udp",
+            Some(local_addr),
+            Wake::None,
+            move |config, stop| {
+                let mut buf = vec![0; config.max_len + 1];
+                while !stop.load(Ordering::Relaxed) {
+                    match socket.recv_from(&mut buf) {
+                        Ok((len, addr)) => deliver_datagram(&config, &buf[..len], &Peer::Udp(addr)),
+                        Err(err) if is_timeout(&err) => {}
+                        Err(_) => return,
+                    }
+                }
+            },
+        )
+    }
+
+    /// Accept connections on `addr`, with RFC 6587 framing.
+    pub fn tcp<A: ToSocketAddrs>(&self, addr: A) -> io::Result<Listener> {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+
+        self.spawn(
+            "syslog-tcp",
+            Some(local_addr),
+            Wake::Tcp(local_addr),
+            move |config, stop| {
+                accept_loop(&config, &stop, || {
+                    let (stream, addr) = listener.accept()?;
+                    Ok((stream, Peer::Tcp(addr)))
+                })
+            },
+        )
+    }
+
+    /// Accept TLS connections on `addr`, usually on port `tls::TLS_PORT`,
+    /// configured by `acceptor`. RFC 5425 mandates octet-counting framing,
+    /// but non-transparent framing is accepted too.
+    #[cfg(feature = "tls")]
+    pub fn tls<A: ToSocketAddrs>(&self, addr: A, acceptor: &tls::Acceptor) -> io::Result<Listener> {
+        let tls_config = acceptor.config()?;
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+
+        self.spawn(
+            "syslog-tls",
+            Some(local_addr),
+            Wake::Tcp(local_addr),
+            move |config, stop| {
+                accept_loop(&config, &stop, || {
+                    let (stream, addr) = listener.accept()?;
+                    let conn =
+                        ServerConnection::new(tls_config.clone()).map_err(io::Error::other)?;
+                    let peer = Peer::Tls {
+                        addr,
+                        subject: None,
+                    };
+                    Ok((StreamOwned::new(conn, stream), peer))
+                })
+            },
+        )
+    }
+
+    /// Receive datagrams on a Unix socket at `path`, like `/dev/log`. The
+    /// socket file is removed on shutdown.
+    #[cfg(unix)]
+    pub fn unix_datagram<P: AsRef<Path>>(&self, path: P) -> io::Result<Listener> {
+        let path = path.as_ref().to_path_buf();
+        let socket = UnixDatagram::bind(&path)?;
+        socket.set_read_timeout(Some(POLL_INTERVAL))?;
+
+        self.spawn(
+            "syslog-unix",
+            None,
+            Wake::Unlink(path),
+            move |config, stop| {
+                let mut buf = vec![0; config.max_len + 1];
+                while !stop.load(Ordering::Relaxed) {
+                    match socket.recv_from(&mut buf) {
+                        Ok((len, addr)) => {
+                            let peer = Peer::Unix(addr.as_pathname().map(Path::to_path_buf));
+                            deliver_datagram(&config, &buf[..len], &peer)
+                        }
+                        Err(err) if is_timeout(&err) => {}
+                        Err(_) => return,
+                    }
+                }
+            },
+        )
+    }
+
+    /// Accept connections on a Unix socket at `path`, with RFC 6587 framing.
+    /// NUL terminated frames, as sent by `syslog(3)` to stream sockets, are
+    /// supported too. The socket file is removed on shutdown.
+    #[cfg(unix)]
+    pub fn unix_stream<P: AsRef<Path>>(&self, path: P) -> io::Result<Listener> {
+        let path = path.as_ref().to_path_buf();
+        let listener = UnixListener::bind(&path)?;
+
+        self.spawn(
+            "syslog-unix",
+            None,
+            Wake::Unix(path),
+            move |config, stop| {
+                accept_loop(&config, &stop, || {
+                    let (stream, addr) = listener.accept()?;
+                    let peer = Peer::Unix(addr.as_pathname().map(Path::to_path_buf));
+                    Ok((stream, peer))
+                })
+            },
+        )
+    }
+
+    fn spawn<F>(
+        &self,
+        name: &str,
+        local_addr: Option<SocketAddr>,
+        wake: Wake,
+        run: F,
+    ) -> io::Result<Listener>
+    where
+        F: FnOnce(Arc<Config<H>>, Arc<AtomicBool>) + Send + 'static,
+    {
+        let config = self.config.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            thread::Builder::new()
+                .name(name.into())
+                .spawn(move || run(config, stop))?
+        };
+
+        Ok(Listener {
+            local_addr,
+            stop,
+            wake,
+            thread: Some(thread),
+        })
+    }
+}
+
+/// How to wake up a listener's thread which is blocked in `accept`.
+enum Wake {
+    None,
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// Nothing to wake up, but the socket file is removed
+    #[cfg(unix)]
+    Unlink(PathBuf),
+}
+
+/// A running listener, dropping it shuts it down.
+pub struct Listener {
+    local_addr: Option<SocketAddr>,
+    stop: Arc<AtomicBool>,
+    wake: Wake,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    /// The address of an UDP or TCP listener, e.g. to find out the port
+    /// after binding port 0.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// Stop accepting connections and receiving messages, and wait until
+    /// all threads are done. Complete frames which were received already are
+    /// still delivered.
+    pub fn shutdown(self) {}
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        match &self.wake {
+            Wake::None => {}
+            Wake::Tcp(addr) => {
+                let mut addr = *addr;
+                if addr.ip().is_unspecified() {
+                    match addr {
+                        SocketAddr::V4(_) => addr.set_ip([127, 0, 0, 1].into()),
+                        SocketAddr::V6(_) => addr.set_ip(std::net::Ipv6Addr::LOCALHOST.into()),
+                    }
+                }
+                let _ = TcpStream::connect(addr);
+            }
+            #[cfg(unix)]
+            Wake::Unix(path) => {
+                let _ = UnixStream::connect(path);
+            }
+            #[cfg(unix)]
+            Wake::Unlink(_) => {}
+        }
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+
+        #[cfg(unix)]
+        if let Wake::Unix(path) | Wake::Unlink(path) = &self.wake {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted
+    )
+}
+
+fn deliver_datagram<H: Handler>(config: &Config<H>, datagram: &[u8], peer: &Peer) {
+    if datagram.len() > config.max_len {
+        config.handler.error(peer, Error::MessageTooLong);
+        return;
+    }
+
+    // some senders terminate datagrams like stream frames
+    let datagram = datagram.strip_suffix(b"\0").unwrap_or(datagram);
+    let datagram = datagram.strip_suffix(b"\n").unwrap_or(datagram);
+    config.deliver(datagram, peer);
+}
+
+/// A stream connection, see `accept_loop`.
+trait Connection: Read + Send + 'static {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// Prepare the connection on its own thread, before reading frames,
+    /// within `timeout`.
+    fn handshake(
+        &mut self,
+        timeout: Option<Duration>,
+        stop: &AtomicBool,
+        peer: &mut Peer,
+    ) -> io::Result<()> {
+        let _ = (timeout, stop, peer);
+        Ok(())
+    }
+}
+
+impl Connection for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(feature = "tls")]
+impl Connection for StreamOwned<ServerConnection, TcpStream> {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.sock.set_read_timeout(timeout)
+    }
+
+    fn handshake(
+        &mut self,
+        timeout: Option<Duration>,
+        stop: &AtomicBool,
+        peer: &mut Peer,
+    ) -> io::Result<()> {
+        let timeout = timeout.map_or(tls::HANDSHAKE_TIMEOUT, |timeout| {
+            timeout.min(tls::HANDSHAKE_TIMEOUT)
+        });
+        tls::handshake(self, timeout, || stop.load(Ordering::Relaxed))?;
+
+        if let Peer::Tls { subject, .. } = peer {
+            *subject = self
+                .conn
+                .peer_certificates()
+                .and_then(|chain| chain.first())
+                .and_then(|cert| tls::subject(cert));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Connection for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Accept connections until `stop` is set, and wait for their threads.
+fn accept_loop<H, C, A>(config: &Arc<Config<H>>, stop: &Arc<AtomicBool>, mut accept: A)
+where
+    H: Handler,
+    C: Connection,
+    A: FnMut() -> io::Result<(C, Peer)>,
+{
+    let mut connections: Vec<JoinHandle<()>> = Vec::new();
+
+    while !stop.load(Ordering::Relaxed) {
+        let (conn, peer) = match accept() {
+            Ok(accepted) => accepted,
+            Err(err) if is_timeout(&err) => continue,
+            // e.g. out of file descriptors, don't spin
+            Err(_) => {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        if stop.load(Ordering::Relaxed) || conn.set_read_timeout(Some(POLL_INTERVAL)).is_err() {
+            continue;
+        }
+
+        connections.retain(|thread| !thread.is_finished());
+        if connections.len() >= config.max_connections {
+            continue;
+        }
+
+        let config = config.clone();
+        let stop = stop.clone();
+        if let Ok(thread) = thread::Builder::new()
+            .name("syslog-conn".into())
+            .spawn(move || read_frames(&config, &stop, conn, peer))
+        {
+            connections.push(thread);
+        }
+    }
+
+    for thread in connections {
+        let _ = thread.join();
+    }
+}
+
+/// Deliver the frames of a connection until it's closed, it's idle for too
+/// long or `stop` is set.
+fn read_frames<H: Handler, C: Connection>(
+    config: &Config<H>,
+    stop: &AtomicBool,
+    mut conn: C,
+    mut peer: Peer,
+) {
+    if conn
+        .handshake(config.idle_timeout, stop, &mut peer)
+        .is_err()
+    {
+        return;
+    }
+    let peer = &peer;
+
+    let mut frames = Frames::default();
+    let mut chunk = vec![0; 16 * 1024];
+    let mut last_frame = Instant::now();
+
+    loop {
+        loop {
+            match frames.next(config.max_len) {
+                Ok(Some(frame)) => {
+                    config.deliver(&frames.buf[frame], peer);
+                    last_frame = Instant::now();
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    config.handler.error(peer, err);
+                    return;
+                }
+            }
+        }
+
+        if stop.load(Ordering::Relaxed)
+            || config
+                .idle_timeout
+                .is_some_and(|timeout| last_frame.elapsed() >= timeout)
+        {
+            return;
+        }
+
+        match conn.read(&mut chunk) {
+            Ok(0) => {
+                if let Some(frame) = frames.rest(config.max_len) {
+                    config.deliver(&frames.buf[frame], peer);
+                }
+                return;
+            }
+            Ok(len) => frames.extend(&chunk[..len]),
+            Err(err) if is_timeout(&err) => {}
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detection() {
+        assert!(is_rfc5424(b"<34>1 2003-10-11T22:14:15.003Z"));
+        assert!(is_rfc5424(b"<34>2 -"));
+        assert!(!is_rfc5424(b"<34>Oct 11 22:14:15"));
+        assert!(!is_rfc5424(b"<34>10 apples"));
+        assert!(!is_rfc5424(b"<34>1"));
+        assert!(!is_rfc5424(b"no pri"));
+    }
+}
@@ -0,0 +1,255 @@
+//! Read syslog messages from a byte stream, e.g. an archived log file.
+//!
+//! Both newline-delimited and octet-counted (RFC 6587) framing are supported,
+//! and they can be mixed in the same stream. A frame starting with at most 10
+//! ASCII digits and a space is octet-counted (`MSG-LEN SP SYSLOG-MSG`), if
+//! `MSG-LEN` is within the maximum length. Anything else is read until the
+//! next `\n` or `\0`.
+
+use std::io::{self, BufRead};
+
+use crate::framing::Frames;
+use crate::rfc5424::parse_message;
+use crate::{Error, Message};
+
+/// The default maximum length of a frame.
+pub const DEFAULT_MAX_LEN: usize = 64 * 1024;
+
+/// A lending iterator over the messages of a `BufRead`.
+///
+/// The internal buffer is reused for every frame, so the returned `Message`
+/// borrows from the `Messages` and must be dropped before reading the next one.
+pub struct Messages<R> {
+    reader: R,
+    frames: Frames,
+    eof: bool,
+    max_len: usize,
+}
+
+impl<R: BufRead> Messages<R> {
+    pub fn new(reader: R) -> Self {
+        Messages {
+            reader,
+            frames: Frames::default(),
+            eof: false,
+            max_len: DEFAULT_MAX_LEN,
+        }
+    }
+
+    /// The maximum length of a frame, `DEFAULT_MAX_LEN` by default. Longer
+    /// lines are skipped up to the next newline, and reported as
+    /// `Error::MessageTooLong`.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// The line number (starts from 1) the most recently read frame starts
+    /// on. Newlines inside octet-counted frames are counted too.
+    pub fn line(&self) -> usize {
+        self.frames.line
+    }
+
+    /// Read and parse the next frame, `None` is returned once the reader
+    /// reaches EOF. Empty lines are skipped. Frames which can't be parsed,
+    /// aren't valid UTF-8 or are too long are returned as `Err(Error)`, and
+    /// reading can go on after them.
+    ///
+    /// `io::Error`s are only returned for I/O failures, and for an
+    /// octet-counted frame which is cut off by EOF.
+    pub fn next_message(&mut self) -> Option<io::Result<Result<Message<&str>, Error>>> {
+        let frame = loop {
+            match self.frames.next(self.max_len) {
+                Ok(Some(frame)) if frame.is_empty() => continue,
+                Ok(Some(frame)) => break frame,
+                Ok(None) => {}
+                Err(err) => return Some(Ok(Err(err))),
+            }
+
+            if self.eof {
+                if self.frames.is_empty() {
+                    return None;
+                }
+                match self.frames.rest(self.max_len) {
+                    Some(frame) => break frame,
+                    None => return Some(Err(io::ErrorKind::UnexpectedEof.into())),
+                }
+            }
+
+            match self.reader.fill_buf() {
+                Ok([]) => self.eof = true,
+                Ok(data) => {
+                    let len = data.len();
+                    self.frames.extend(data);
+                    self.reader.consume(len);
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err)),
+            }
+        };
+
+        let frame = &self.frames.buf[frame];
+        if core::str::from_utf8(frame).is_err() {
+            return Some(Ok(Err(Error::InvalidUtf8)));
+        }
+
+        Some(Ok(parse_message(frame)))
+    }
+
+    /// Call `on_message` for every message in the stream. Frames that cannot
+    /// be parsed are skipped, and reported to `on_error` with their line number.
+    pub fn for_each<F, E>(&mut self, mut on_message: F, mut on_error: E) -> io::Result<()>
+    where
+        F: FnMut(Message<&str>),
+        E: FnMut(usize, Error),
+    {
+        while let Some(result) = self.next_message() {
+            match result? {
+                Ok(msg) => on_message(msg),
+                Err(err) => on_error(self.frames.line, err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mixed_framing() {
+        let input = b"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - first\r\n\
+                     \n\
+                     bad line\n\
+                     45 <34>1 - mymachine.example.com su - - - second\n\
+                     <34>1 - mymachine.example.com su - - - third";
+
+        let mut msgs = vec![];
+        let mut errs = vec![];
+        Messages::new(&input[..])
+            .for_each(
+                |msg| msgs.push(msg.msg.to_string()),
+                |line, err| errs.push((line, err)),
+            )
+            .unwrap();
+
+        assert_eq!(msgs, ["first", "second", "third"]);
+        assert_eq!(errs, [(3, Error::ExpectedChar('<'))]);
+    }
+
+    #[test]
+    fn truncated_octet_counted_frame() {
+        let input = b"100 <34>1 - - - - - -";
+
+        let mut msgs = Messages::new(&input[..]);
+        let err = msgs.next_message().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input = b"<34>1 - - - - - - \xff\xfe\n<34>1 - - - - - - ok";
+
+        let mut msgs = vec![];
+        let mut errs = vec![];
+        Messages::new(&input[..])
+            .for_each(
+                |msg| msgs.push(msg.msg.to_string()),
+                |line, err| errs.push((line, err)),
+            )
+            .unwrap();
+
+        assert_eq!(msgs, ["ok"]);
+        assert_eq!(errs, [(1, Error::InvalidUtf8)]);
+    }
+
+    #[test]
+    fn bad_lines() {
+        // digits without a space, or with a count which is too long, are not
+        // an octet count
+        let input = b"<34>1 - - - - - - first\n\
+                     2024-01-01 foo\n\
+                     99999999999 bar\n\
+                     <34>1 - - - - - - second\n";
+
+        let mut msgs = vec![];
+        let mut errs = vec![];
+        Messages::new(&input[..])
+            .for_each(
+                |msg| msgs.push(msg.msg.to_string()),
+                |line, _| errs.push(line),
+            )
+            .unwrap();
+
+        assert_eq!(msgs, ["first", "second"]);
+        assert_eq!(errs, [2, 3]);
+    }
+
+    #[test]
+    fn line_numbers() {
+        let input = b"21 <34>1 - - - - - - a\nb\n\
+                     \n\
+                     bad\n\
+                     <34>1 - - - - - - c";
+
+        let mut msgs = Messages::new(&input[..]);
+        assert_eq!(msgs.next_message().unwrap().unwrap().unwrap().msg, "a\nb");
+        assert_eq!(msgs.line(), 1);
+        assert!(msgs.next_message().unwrap().unwrap().is_err());
+        assert_eq!(msgs.line(), 4);
+        assert_eq!(msgs.next_message().unwrap().unwrap().unwrap().msg, "c");
+        assert_eq!(msgs.line(), 5);
+        assert!(msgs.next_message().is_none());
+    }
+
+    #[test]
+    fn max_len() {
+        for input in [
+            &b"99999999999999999999999 <34>1 - - - - - -"[..],
+            b"99999999999 <34>1 - - - - - -",
+            b"18 <34>1 - - - - - - x",
+            b"<34>1 - - - - - this line is too long\n",
+            b"<34>1 - - - - - this line is too long",
+        ] {
+            let mut msgs = Messages::new(input).max_len(17);
+            let err = msgs.next_message().unwrap().unwrap();
+            assert_eq!(err.err(), Some(Error::MessageTooLong), "{input:?}");
+            assert!(msgs.next_message().is_none(), "{input:?}");
+        }
+
+        // the rest of a line which is too long is skipped
+        let input = b"<34>1 - - - - - this line is too long\n\
+                     17 <34>1 - - - - - -\n\
+                     <34>1 - - - - - -\n";
+        let mut msgs = Messages::new(&input[..]).max_len(17);
+        assert!(msgs.next_message().unwrap().unwrap().is_err());
+        for line in [2, 3] {
+            assert_eq!(msgs.next_message().unwrap().unwrap().unwrap().msg, "");
+            assert_eq!(msgs.line(), line);
+        }
+        assert!(msgs.next_message().is_none());
+    }
+
+    #[test]
+    fn chunks() {
+        // a reader which returns a byte at a time
+        let input = b"<34>1 - - - - - this line is too long\n\
+                     17 <34>1 - - - - - -<34>1 - - - - - -\n";
+        let reader = io::BufReader::with_capacity(1, &input[..]);
+
+        let mut msgs = vec![];
+        let mut errs = vec![];
+        Messages::new(reader)
+            .max_len(17)
+            .for_each(
+                |msg| msgs.push(msg.msg.to_string()),
+                |line, err| errs.push((line, err)),
+            )
+            .unwrap();
+
+        assert_eq!(msgs, ["", ""]);
+        assert_eq!(errs, [(1, Error::MessageTooLong)]);
+    }
+}
@@ -1,3 +1,11 @@
+use core::fmt;
+use core::ops::Range;
+
+use alloc::vec::Vec;
+
+use crate::rfc5424::{parse_param_key, parse_param_value, parse_sd_id, skip_structured_element};
+use crate::Error;
+
 #[derive(Clone, Debug, Eq)]
 pub struct StructuredElement<S: AsRef<str> + Ord + Clone> {
     pub id: S,
@@ -24,3 +32,296 @@ impl<S: AsRef<str> + Ord + Clone> PartialEq for StructuredElement<S> {
             })
     }
 }
+
+/// Start and end offsets of a field in the raw message
+type Span = (usize, usize);
+
+/// Reusable storage for the structured data of `rfc5424::parse_message_in`.
+///
+/// Only offsets into the input are stored, so a `Scratch` is not tied to
+/// any input, and can be reused for as many messages as you like.
+#[derive(Clone, Debug, Default)]
+pub struct Scratch {
+    pub(crate) elements: Vec<(Span, Range<usize>)>,
+    pub(crate) params: Vec<(Span, Span)>,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.elements.clear();
+        self.params.clear();
+    }
+}
+
+#[inline]
+fn resolve(buf: &[u8], (start, end): Span) -> &str {
+    unsafe { core::str::from_utf8_unchecked(&buf[start..end]) }
+}
+
+/// Structured data borrowed from the input and a `Scratch`.
+#[derive(Clone, Copy)]
+pub struct StructuredDataRef<'a> {
+    buf: &'a [u8],
+    elements: &'a [(Span, Range<usize>)],
+    params: &'a [(Span, Span)],
+}
+
+impl<'a> StructuredDataRef<'a> {
+    pub(crate) fn new(buf: &'a [u8], scratch: &'a Scratch) -> Self {
+        StructuredDataRef {
+            buf,
+            elements: &scratch.elements,
+            params: &scratch.params,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = StructuredElementRef<'a>> + 'a {
+        let StructuredDataRef {
+            buf,
+            elements,
+            params,
+        } = *self;
+
+        elements
+            .iter()
+            .map(move |(id, range)| StructuredElementRef {
+                buf,
+                id: resolve(buf, *id),
+                params: &params[range.clone()],
+            })
+    }
+
+    /// Find the first element with the SD-ID
+    pub fn get(&self, id: &str) -> Option<StructuredElementRef<'a>> {
+        self.iter().find(|element| element.id == id)
+    }
+
+    /// Copy the elements into `StructuredElement`s, the strings are still
+    /// borrowed from the input.
+    pub fn to_vec(&self) -> Vec<StructuredElement<&'a str>> {
+        self.iter()
+            .map(|element| StructuredElement {
+                id: element.id,
+                params: element.params().collect(),
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for StructuredDataRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for StructuredDataRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+    }
+}
+
+impl Eq for StructuredDataRef<'_> {}
+
+/// A single SD-ELEMENT of `StructuredDataRef`
+#[derive(Clone, Copy)]
+pub struct StructuredElementRef<'a> {
+    buf: &'a [u8],
+    pub id: &'a str,
+    params: &'a [(Span, Span)],
+}
+
+impl<'a> StructuredElementRef<'a> {
+    pub fn params(&self) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+        let buf = self.buf;
+
+        self.params
+            .iter()
+            .map(move |(key, value)| (resolve(buf, *key), resolve(buf, *value)))
+    }
+
+    /// Find the value of the first param named `key`
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.params().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+}
+
+impl fmt::Debug for StructuredElementRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StructuredElementRef")
+            .field("id", &self.id)
+            .field("params", &self.params().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl PartialEq for StructuredElementRef<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.params.len() == other.params.len()
+            && self.params().zip(other.params()).all(|(a, b)| a == b)
+    }
+}
+
+impl Eq for StructuredElementRef<'_> {}
+
+/// Unparsed structured data, elements are parsed one by one when iterating.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct RawStructuredData<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> RawStructuredData<'a> {
+    pub(crate) fn new(raw: &'a [u8]) -> Self {
+        RawStructuredData { raw }
+    }
+
+    /// The structured data as is, empty if there is none
+    pub fn as_str(&self) -> &'a str {
+        unsafe { core::str::from_utf8_unchecked(self.raw) }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.raw.is_empty()
+    }
+
+    pub fn elements(&self) -> RawElements<'a> {
+        RawElements {
+            raw: self.raw,
+            offset: 0,
+        }
+    }
+
+    /// Parse all elements at once, just like `rfc5424::parse_message` does
+    pub fn parse(&self) -> Result<Vec<StructuredElement<&'a str>>, Error> {
+        self.elements()
+            .map(|element| {
+                let element = element?;
+                Ok(StructuredElement {
+                    id: element.id,
+                    params: element.params().collect::<Result<_, _>>()?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for RawStructuredData<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RawStructuredData")
+            .field(&self.as_str())
+            .finish()
+    }
+}
+
+/// Iterator over the elements of `RawStructuredData`, it stops after the
+/// first error.
+pub struct RawElements<'a> {
+    raw: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for RawElements<'a> {
+    type Item = Result<RawStructuredElement<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.raw.len() {
+            return None;
+        }
+
+        let start = self.offset;
+        if let Err(err) = skip_structured_element(self.raw, &mut self.offset) {
+            self.offset = self.raw.len();
+            return Some(Err(err));
+        }
+
+        // e.g. `[exampleSDID@32473 iut="3"]`
+        let element = &self.raw[start..self.offset];
+        if element[1] == b']' {
+            // empty structured element, e.g. `[]`
+            return Some(Ok(RawStructuredElement {
+                id: "",
+                params: b"",
+            }));
+        }
+
+        let mut offset = 1;
+        let (id, has_params) = parse_sd_id(element, &mut offset);
+        let params = if has_params { &element[offset..] } else { b"" };
+
+        Some(Ok(RawStructuredElement { id, params }))
+    }
+}
+
+/// A single SD-ELEMENT of `RawStructuredData`, its params are not parsed yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RawStructuredElement<'a> {
+    pub id: &'a str,
+    params: &'a [u8],
+}
+
+impl<'a> RawStructuredElement<'a> {
+    pub fn params(&self) -> RawParams<'a> {
+        RawParams {
+            raw: self.params,
+            offset: 0,
+        }
+    }
+}
+
+/// Iterator over the params of a `RawStructuredElement`, it stops after the
+/// first error.
+pub struct RawParams<'a> {
+    raw: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> RawParams<'a> {
+    fn parse_param(&mut self) -> Result<(&'a str, &'a str), Error> {
+        let buf = self.raw;
+        let offset = &mut self.offset;
+
+        let key = parse_param_key(buf, offset)?;
+        if buf[*offset] != b'=' {
+            return Err(Error::ExpectedChar('='));
+        }
+        *offset += 1;
+
+        let value = parse_param_value(buf, offset)?;
+        match buf.get(*offset) {
+            Some(b']') => *offset = buf.len(),
+            Some(b' ') => *offset += 1,
+            _ => return Err(Error::InvalidStructuredData),
+        }
+
+        Ok((key, value))
+    }
+}
+
+impl<'a> Iterator for RawParams<'a> {
+    type Item = Result<(&'a str, &'a str), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.raw.len() {
+            return None;
+        }
+
+        let result = self.parse_param();
+        if result.is_err() {
+            self.offset = self.raw.len();
+        }
+
+        Some(result)
+    }
+}
@@ -0,0 +1,812 @@
+//! A lightweight RFC 3339 timestamp, with a fast parser and formatter.
+//!
+//! ```
+//! use syslog::timestamp::{format_rfc3339, parse, MAX_LEN};
+//!
+//! let (ts, len) = parse(b"2003-10-11T22:14:15.003Z mymachine").unwrap();
+//! assert_eq!(len, 24);
+//!
+//! let mut buf = [0u8; MAX_LEN];
+//! let n = format_rfc3339(&ts, 6, &mut buf).unwrap();
+//! assert_eq!(&buf[..n], b"2003-10-11T22:14:15.003000Z");
+//! ```
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Timelike};
+
+use crate::Error;
+
+/// The fields of a RFC 3339 timestamp, without any calendar validation.
+///
+/// Parsing into a `Timestamp` is cheap, the date is validated only when
+/// converting it into `chrono`, `time` (with the `time` feature) or `jiff`
+/// (with the `jiff` feature) types.
+///
+/// `second` might be 60 for leap seconds. chrono represents them as second 59
+/// with nanos beyond 1_000_000_000, `time` and `jiff` can't represent them at
+/// all, so they are clamped to the last nanosecond of second 59.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Timestamp {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub nanos: u32,
+    /// Offset from UTC, in seconds
+    pub offset: i32,
+    /// `false` if the input had no offset at all, which only happens with
+    /// `parse_lenient`, `offset` is 0 then and the time is in some unknown
+    /// local timezone.
+    pub has_offset: bool,
+}
+
+// Every byte must be in `b'0'..=b'9'`, i.e. the high nibble is 3, and adding 6
+// doesn't overflow the low nibble. The second check can't carry into the next
+// byte, once the first one holds.
+#[inline]
+fn is_digits_u16(chunk: u16) -> bool {
+    chunk & 0xf0f0 == 0x3030 && chunk.wrapping_add(0x0606) & 0xf0f0 == 0x3030
+}
+
+#[inline]
+fn is_digits_u32(chunk: u32) -> bool {
+    chunk & 0xf0f0f0f0 == 0x30303030 && chunk.wrapping_add(0x06060606) & 0xf0f0f0f0 == 0x30303030
+}
+
+#[inline]
+fn convert_2_digits(digits: &[u8]) -> Result<u32, Error> {
+    let bytes: [u8; 2] = digits.try_into().unwrap();
+    let chunk = u16::from_ne_bytes(bytes);
+    if !is_digits_u16(chunk) {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let chunk = chunk as u32;
+    let lower = (chunk & 0x0f00) >> 8;
+    let upper = (chunk & 0x000f) * 10;
+
+    Ok(lower + upper)
+}
+
+#[inline]
+fn convert_4_digits(digits: &[u8]) -> Result<u32, Error> {
+    let bytes: [u8; 4] = digits.try_into().unwrap();
+    let mut chunk = u32::from_ne_bytes(bytes);
+    if !is_digits_u32(chunk) {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let mut lower = (chunk & 0x0f000f00) >> 8;
+    let mut upper = (chunk & 0x000f000f) * 10;
+
+    chunk = lower + upper;
+
+    lower = (chunk & 0x00ff0000) >> 16;
+    upper = (chunk & 0x000000ff) * 100;
+
+    Ok(lower + upper)
+}
+
+/// Parse a [RFC 3339](https://datatracker.ietf.org/doc/html/rfc3339) timestamp
+/// from the start of `buf`, and return it with the number of bytes consumed.
+///
+/// Only the syntax is checked, the date is validated when converting the
+/// `Timestamp` into other types. Some edge cases are handled loosely, since
+/// senders are not always as strict as the RFCs:
+///
+///  * leap seconds, e.g. `1990-12-31T23:59:60Z`, are kept as second 60, see
+///    `Timestamp` for how they are converted.
+///  * the date/time separator can be lowercase `t`, as RFC 3339 allows.
+///  * RFC 5424 allows at most 6 fractional digits, but more are accepted, and
+///    everything beyond nanoseconds is truncated.
+///  * `-00:00`, the "unknown local offset" of RFC 3339, is treated as UTC.
+pub fn parse(buf: &[u8]) -> Result<(Timestamp, usize), Error> {
+    let mut offset = 0;
+    let timestamp = parse_rfc3339(buf, &mut offset)?;
+
+    Ok((timestamp, offset))
+}
+
+/// Parse rfc3339 into a `Timestamp`, starting at `offset`, the date is not
+/// validated here.
+#[inline]
+pub(crate) fn parse_rfc3339(buf: &[u8], offset: &mut usize) -> Result<Timestamp, Error> {
+    // 20 is the length of `1990-12-31T23:59:60Z`
+    if buf.len().saturating_sub(*offset) < 20 {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    parse_datetime(buf, offset, false)
+}
+
+/// Parse a timestamp from the start of `buf` like `parse`, but also accept
+/// the variants that some devices emit instead of RFC 3339:
+///
+///  * a space as the date/time separator, e.g. `2023-04-07 12:52:00`
+///  * no offset at all, e.g. `2023-04-07T12:52:00`, `has_offset` of the
+///    returned `Timestamp` is `false` then, and `offset` is 0.
+///  * offsets without a colon, e.g. `+0800`
+///  * Unix epoch seconds, with an optional fraction, e.g. `1680871920.654`
+pub fn parse_lenient(buf: &[u8]) -> Result<(Timestamp, usize), Error> {
+    let mut offset = 0;
+    let timestamp = parse_lenient_at(buf, &mut offset)?;
+
+    Ok((timestamp, offset))
+}
+
+pub(crate) fn parse_lenient_at(buf: &[u8], offset: &mut usize) -> Result<Timestamp, Error> {
+    let rest = buf.get(*offset..).ok_or(Error::InvalidTimestamp)?;
+
+    // `YYYY-` can never start a epoch timestamp
+    if rest.get(4) != Some(&b'-') {
+        return parse_epoch(buf, offset);
+    }
+
+    // 19 is the length of `2023-04-07T12:52:00`
+    if rest.len() < 19 {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    parse_datetime(buf, offset, true)
+}
+
+/// The caller must make sure that there are at least 19 bytes after `offset`.
+#[inline]
+fn parse_datetime(buf: &[u8], offset: &mut usize, lenient: bool) -> Result<Timestamp, Error> {
+    let len = buf.len();
+
+    let year = convert_4_digits(&buf[*offset..*offset + 4])? as i32;
+
+    if buf[*offset + 4] != b'-' {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    *offset += 5;
+    let month = convert_2_digits(&buf[*offset..*offset + 2])? as u8;
+
+    if buf[*offset + 2] != b'-' {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    *offset += 3;
+    let day = convert_2_digits(&buf[*offset..*offset + 2])? as u8;
+
+    match buf[*offset + 2] {
+        b'T' | b't' => {}
+        b' ' if lenient => {}
+        _ => return Err(Error::InvalidTimestamp),
+    }
+    *offset += 3;
+
+    let hour = convert_2_digits(&buf[*offset..*offset + 2])? as u8;
+    if buf[*offset + 2] != b':' {
+        return Err(Error::InvalidTimestamp);
+    }
+    *offset += 3;
+
+    let minute = convert_2_digits(&buf[*offset..*offset + 2])? as u8;
+    if buf[*offset + 2] != b':' {
+        return Err(Error::InvalidTimestamp);
+    }
+    *offset += 3;
+
+    let second = convert_2_digits(&buf[*offset..*offset + 2])? as u8;
+    *offset += 2;
+
+    let mut timestamp = Timestamp {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+        nanos: 0,
+        offset: 0,
+        has_offset: true,
+    };
+
+    if let Some(b'.' | b',') = buf.get(*offset) {
+        *offset += 1;
+        timestamp.nanos = parse_fraction(buf, offset);
+    }
+
+    let sign = match buf.get(*offset) {
+        Some(b'z' | b'Z') => {
+            // no offset. e.g. `1990-12-31T23:59:60Z`
+            *offset += 1;
+            return Ok(timestamp);
+        }
+        Some(b'+') => 1,
+        Some(b'-') => -1,
+        None | Some(b' ') if lenient => {
+            timestamp.has_offset = false;
+            return Ok(timestamp);
+        }
+        _ => return Err(Error::InvalidTimestamp),
+    };
+
+    *offset += 1;
+    if len - *offset < 4 {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let h = convert_2_digits(&buf[*offset..*offset + 2])? as i32;
+    let m = if buf[*offset + 2] == b':' && len - *offset >= 5 {
+        *offset += 5;
+        convert_2_digits(&buf[*offset - 2..*offset])? as i32
+    } else if lenient {
+        *offset += 4;
+        convert_2_digits(&buf[*offset - 2..*offset])? as i32
+    } else {
+        return Err(Error::InvalidTimestamp);
+    };
+    if m >= 60 {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    timestamp.offset = sign * (h * 60 * 60 + m * 60);
+
+    Ok(timestamp)
+}
+
+/// Parse up to 9 fractional digits as nanoseconds, digits beyond that are
+/// skipped.
+fn parse_fraction(buf: &[u8], offset: &mut usize) -> u32 {
+    let len = buf.len();
+    let mut nanos = 0u32;
+    let mut count = 0;
+    let end = core::cmp::min(*offset + 9, len);
+    for ch in &buf[*offset..end] {
+        if !ch.is_ascii_digit() {
+            break;
+        }
+
+        count += 1;
+        nanos = (nanos * 10) + (ch - b'0') as u32;
+    }
+
+    *offset += count;
+
+    // truncate digits beyond nanoseconds
+    while *offset < len && buf[*offset].is_ascii_digit() {
+        *offset += 1;
+    }
+
+    nanos * 10u32.pow(9 - count as u32)
+}
+
+fn parse_epoch(buf: &[u8], offset: &mut usize) -> Result<Timestamp, Error> {
+    let start = *offset;
+    let mut secs = 0i64;
+    while let Some(ch) = buf.get(*offset).filter(|ch| ch.is_ascii_digit()) {
+        // 12 digits are good until year 33658
+        if *offset - start == 12 {
+            return Err(Error::InvalidTimestamp);
+        }
+
+        secs = secs * 10 + (ch - b'0') as i64;
+        *offset += 1;
+    }
+    if *offset == start {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let mut nanos = 0;
+    if buf.get(*offset) == Some(&b'.') {
+        *offset += 1;
+        nanos = parse_fraction(buf, offset);
+    }
+
+    if !matches!(buf.get(*offset), None | Some(b' ')) {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let days = secs.div_euclid(86400);
+    let secs = secs.rem_euclid(86400) as u32;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + (month <= 2) as i64) as i32;
+
+    Ok(Timestamp {
+        year,
+        month,
+        day,
+        hour: (secs / 3600) as u8,
+        minute: (secs % 3600 / 60) as u8,
+        second: (secs % 60) as u8,
+        nanos,
+        offset: 0,
+        has_offset: true,
+    })
+}
+
+/// The longest output of `format_rfc3339`, e.g. `2003-10-11T22:14:15.123456789+08:00`
+pub const MAX_LEN: usize = 35;
+
+/// Write `ts` into `buf` as RFC 3339, with `precision` fractional digits (at
+/// most 9), and return the number of bytes written.
+///
+/// UTC is written as `Z`, and offsets are truncated to whole minutes. Years
+/// beyond `0..=9999` can't be represented, and `Error::BufferTooSmall` is
+/// returned if `buf` is shorter than the output, which never happens when it
+/// is `MAX_LEN` long.
+pub fn format_rfc3339(ts: &Timestamp, precision: u8, buf: &mut [u8]) -> Result<usize, Error> {
+    if !(0..=9999).contains(&ts.year)
+        || ts.month > 99
+        || ts.day > 99
+        || ts.hour > 99
+        || ts.minute > 99
+        || ts.second > 99
+        || ts.offset.unsigned_abs() >= 100 * 3600
+    {
+        return Err(Error::InvalidTimestamp);
+    }
+
+    let precision = precision.min(9) as usize;
+    let mut len = 19;
+    if precision > 0 {
+        len += 1 + precision;
+    }
+    len += if ts.offset == 0 { 1 } else { 6 };
+    if buf.len() < len {
+        return Err(Error::BufferTooSmall);
+    }
+
+    let year = ts.year as u32;
+    write_2_digits(&mut buf[0..2], year / 100);
+    write_2_digits(&mut buf[2..4], year % 100);
+    buf[4] = b'-';
+    write_2_digits(&mut buf[5..7], ts.month as u32);
+    buf[7] = b'-';
+    write_2_digits(&mut buf[8..10], ts.day as u32);
+    buf[10] = b'T';
+    write_2_digits(&mut buf[11..13], ts.hour as u32);
+    buf[13] = b':';
+    write_2_digits(&mut buf[14..16], ts.minute as u32);
+    buf[16] = b':';
+    write_2_digits(&mut buf[17..19], ts.second as u32);
+
+    let mut pos = 19;
+    if precision > 0 {
+        buf[pos] = b'.';
+        pos += 1;
+
+        let mut fraction = ts.nanos.min(999_999_999) / 10u32.pow(9 - precision as u32);
+        for ch in buf[pos..pos + precision].iter_mut().rev() {
+            *ch = b'0' + (fraction % 10) as u8;
+            fraction /= 10;
+        }
+        pos += precision;
+    }
+
+    if ts.offset == 0 {
+        buf[pos] = b'Z';
+    } else {
+        let offset = ts.offset.unsigned_abs();
+        buf[pos] = if ts.offset < 0 { b'-' } else { b'+' };
+        write_2_digits(&mut buf[pos + 1..pos + 3], offset / 3600);
+        buf[pos + 3] = b':';
+        write_2_digits(&mut buf[pos + 4..pos + 6], offset % 3600 / 60);
+    }
+
+    Ok(len)
+}
+
+#[inline]
+fn write_2_digits(buf: &mut [u8], value: u32) {
+    buf[0] = b'0' + (value / 10) as u8;
+    buf[1] = b'0' + (value % 10) as u8;
+}
+
+impl Timestamp {
+    /// Clamp a leap second to the last nanosecond of second 59, for
+    /// types that can't represent leap seconds.
+    #[cfg(any(feature = "time", feature = "jiff"))]
+    #[inline]
+    fn without_leap_second(&self) -> (u8, u32) {
+        if self.second == 60 {
+            (59, 999_999_999)
+        } else {
+            (self.second, self.nanos)
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for DateTime<FixedOffset> {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let (second, nanos) = if ts.second == 60 && ts.nanos < 1_000_000_000 {
+            (59, ts.nanos + 1_000_000_000)
+        } else {
+            (ts.second as u32, ts.nanos)
+        };
+
+        let offset = FixedOffset::east_opt(ts.offset).ok_or(Error::OutOfRangeTimezone)?;
+        let datetime = NaiveDate::from_ymd_opt(ts.year, ts.month as u32, ts.day as u32)
+            .ok_or(Error::InvalidTimestamp)?
+            .and_hms_nano_opt(ts.hour as u32, ts.minute as u32, second, nanos)
+            .ok_or(Error::InvalidTimestamp)?;
+
+        // DateTime::from_local() takes a lot time, it was almost 40% of the
+        // timestamp benchmark, converting to UTC ourself is much cheaper.
+        let utc = datetime
+            .checked_sub_offset(offset)
+            .ok_or(Error::InvalidTimestamp)?;
+
+        Ok(DateTime::from_naive_utc_and_offset(utc, offset))
+    }
+}
+
+impl From<DateTime<FixedOffset>> for Timestamp {
+    /// The local date and time in the offset of `ts`, a leap second becomes
+    /// second 60.
+    #[inline]
+    fn from(ts: DateTime<FixedOffset>) -> Self {
+        let local = ts.naive_local();
+        let (second, nanos) = match local.nanosecond() {
+            nanos if nanos >= 1_000_000_000 => (60, nanos - 1_000_000_000),
+            nanos => (local.second() as u8, nanos),
+        };
+
+        Timestamp {
+            year: local.year(),
+            month: local.month() as u8,
+            day: local.day() as u8,
+            hour: local.hour() as u8,
+            minute: local.minute() as u8,
+            second,
+            nanos,
+            offset: ts.offset().local_minus_utc(),
+            has_offset: true,
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let offset = time::UtcOffset::from_whole_seconds(ts.offset)
+            .map_err(|_| Error::OutOfRangeTimezone)?;
+        let month = time::Month::try_from(ts.month).map_err(|_| Error::InvalidTimestamp)?;
+        let date = time::Date::from_calendar_date(ts.year, month, ts.day)
+            .map_err(|_| Error::InvalidTimestamp)?;
+        let (second, nanos) = ts.without_leap_second();
+        let time = time::Time::from_hms_nano(ts.hour, ts.minute, second, nanos)
+            .map_err(|_| Error::InvalidTimestamp)?;
+
+        Ok(time::PrimitiveDateTime::new(date, time).assume_offset(offset))
+    }
+}
+
+#[cfg(feature = "jiff")]
+impl TryFrom<Timestamp> for jiff::civil::DateTime {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let year = i16::try_from(ts.year).map_err(|_| Error::InvalidTimestamp)?;
+        let (second, nanos) = ts.without_leap_second();
+
+        jiff::civil::DateTime::new(
+            year,
+            ts.month as i8,
+            ts.day as i8,
+            ts.hour as i8,
+            ts.minute as i8,
+            second as i8,
+            nanos as i32,
+        )
+        .map_err(|_| Error::InvalidTimestamp)
+    }
+}
+
+#[cfg(feature = "jiff")]
+impl TryFrom<Timestamp> for jiff::Timestamp {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let offset =
+            jiff::tz::Offset::from_seconds(ts.offset).map_err(|_| Error::OutOfRangeTimezone)?;
+
+        jiff::civil::DateTime::try_from(ts)?
+            .to_zoned(jiff::tz::TimeZone::fixed(offset))
+            .map(|zoned| zoned.timestamp())
+            .map_err(|_| Error::InvalidTimestamp)
+    }
+}
+
+#[cfg(feature = "jiff")]
+impl TryFrom<Timestamp> for jiff::Zoned {
+    type Error = Error;
+
+    #[inline]
+    fn try_from(ts: Timestamp) -> Result<Self, Self::Error> {
+        let offset =
+            jiff::tz::Offset::from_seconds(ts.offset).map_err(|_| Error::OutOfRangeTimezone)?;
+
+        jiff::civil::DateTime::try_from(ts)?
+            .to_zoned(jiff::tz::TimeZone::fixed(offset))
+            .map_err(|_| Error::InvalidTimestamp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lenient() {
+        for (input, len, want) in [
+            ("2023-04-07 12:52:00 host", 19, "2023-04-07T12:52:00Z"),
+            ("2023-04-07T12:52:00", 19, "2023-04-07T12:52:00Z"),
+            (
+                "2023-04-07T12:52:00.654+0800",
+                28,
+                "2023-04-07T12:52:00.654+08:00",
+            ),
+            (
+                "2023-04-07 12:52:00-05:30 host",
+                25,
+                "2023-04-07T12:52:00.000-05:30",
+            ),
+            ("2023-04-07t12:52:00z", 20, "2023-04-07T12:52:00Z"),
+            ("1680871920", 10, "2023-04-07T12:52:00Z"),
+            ("1680871920.654321 host", 17, "2023-04-07T12:52:00.654321Z"),
+            ("0", 1, "1970-01-01T00:00:00Z"),
+            ("951782400", 9, "2000-02-29T00:00:00Z"),
+        ] {
+            let (ts, n) = parse_lenient(input.as_bytes()).unwrap();
+            assert_eq!(n, len, "{input}");
+
+            let want = DateTime::parse_from_rfc3339(want).unwrap();
+            assert_eq!(DateTime::try_from(ts), Ok(want), "{input}");
+        }
+
+        for input in [
+            "2023-04-07 12:52:00",
+            "2023-04-07T12:52:00 host",
+            "2023-04-07 12:52:00.1",
+        ] {
+            let (ts, _) = parse_lenient(input.as_bytes()).unwrap();
+            assert!(!ts.has_offset, "{input}");
+        }
+
+        for input in [
+            "2023-04-07T12:52:00+0800",
+            "2023-04-07T12:52:00-00:00",
+            "1680871920",
+        ] {
+            let (ts, _) = parse_lenient(input.as_bytes()).unwrap();
+            assert!(ts.has_offset, "{input}");
+        }
+
+        for input in [
+            "",
+            "2023-04-07_12:52:00",
+            "2023-04-07T12:52:00+08",
+            "2023-04-07T12:52:00+0860",
+            "2023-04-07T12:52:00X",
+            "1680871920x",
+            "1680871920000000",
+        ] {
+            assert!(parse_lenient(input.as_bytes()).is_err(), "{input}");
+        }
+
+        // the variants are still rejected by the strict parser
+        for input in [
+            "2023-04-07 12:52:00Z",
+            "2023-04-07T12:52:00 host",
+            "2023-04-07T12:52:00+0800",
+            "1680871920",
+        ] {
+            assert!(parse(input.as_bytes()).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn format() {
+        let mut buf = [0u8; MAX_LEN];
+
+        for (input, precision, want) in [
+            ("2003-10-11T22:14:15.003Z", 3, "2003-10-11T22:14:15.003Z"),
+            ("2003-10-11T22:14:15.003Z", 0, "2003-10-11T22:14:15Z"),
+            ("2003-10-11T22:14:15.003Z", 6, "2003-10-11T22:14:15.003000Z"),
+            (
+                "2003-10-11T22:14:15.123456789Z",
+                12,
+                "2003-10-11T22:14:15.123456789Z",
+            ),
+            (
+                "2003-08-24T05:14:15.000003-07:00",
+                6,
+                "2003-08-24T05:14:15.000003-07:00",
+            ),
+            ("0001-01-01T00:00:00+14:30", 0, "0001-01-01T00:00:00+14:30"),
+            ("1990-12-31T23:59:60Z", 0, "1990-12-31T23:59:60Z"),
+        ] {
+            let (ts, _) = parse(input.as_bytes()).unwrap();
+            let n = format_rfc3339(&ts, precision, &mut buf).unwrap();
+            assert_eq!(core::str::from_utf8(&buf[..n]).unwrap(), want);
+        }
+    }
+
+    #[test]
+    fn format_errors() {
+        let (ts, _) = parse(b"2003-10-11T22:14:15.003+08:00").unwrap();
+        let mut buf = [0u8; 29];
+        assert_eq!(format_rfc3339(&ts, 3, &mut buf), Ok(29));
+        assert_eq!(format_rfc3339(&ts, 4, &mut buf), Err(Error::BufferTooSmall));
+
+        let ts = Timestamp { year: 10000, ..ts };
+        assert_eq!(
+            format_rfc3339(&ts, 3, &mut buf),
+            Err(Error::InvalidTimestamp)
+        );
+    }
+
+    #[test]
+    fn format_roundtrip() {
+        let mut buf = [0u8; MAX_LEN];
+
+        for input in [
+            "1985-04-12T23:20:50.52Z",
+            "1996-12-19T16:39:57-08:00",
+            "2003-10-11T22:14:15.123456789+05:45",
+        ] {
+            let (ts, len) = parse(input.as_bytes()).unwrap();
+            assert_eq!(len, input.len());
+
+            let n = format_rfc3339(&ts, 9, &mut buf).unwrap();
+            assert_eq!(parse(&buf[..n]), Ok((ts, n)));
+        }
+    }
+
+    #[test]
+    fn swar_digits() {
+        for chunk in 0..=u16::MAX {
+            let want = chunk.to_ne_bytes().iter().all(u8::is_ascii_digit);
+            assert_eq!(is_digits_u16(chunk), want, "{chunk:#06x}");
+        }
+
+        for bytes in [
+            *b"2003", *b"0000", *b"9999", *b"20a3", *b"/999", *b"999:", [0xff; 4],
+        ] {
+            let want = bytes.iter().all(u8::is_ascii_digit);
+            assert_eq!(is_digits_u32(u32::from_ne_bytes(bytes)), want, "{bytes:?}");
+        }
+    }
+
+    #[test]
+    fn test_convert_2_digits() {
+        for i in 0..99 {
+            let s = format!("{:02}", i);
+            let actual = convert_2_digits(&s.as_bytes()[..2]).unwrap();
+            assert_eq!(actual, i);
+        }
+
+        for input in [b" 1", b"1/", b"0:", b"a1", b"1A", b"\x001", b"\xf1\xf1"] {
+            assert_eq!(
+                convert_2_digits(input),
+                Err(Error::InvalidTimestamp),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_convert_4_digits() {
+        for i in 0..9999 {
+            let s = format!("{:04}", i);
+            let actual = convert_4_digits(&s.as_bytes()[..4]).unwrap();
+            assert_eq!(i, actual);
+        }
+
+        for input in [b"20a3", b" 203", b"200/", b"20:3", b"\xff003"] {
+            assert_eq!(
+                convert_4_digits(input),
+                Err(Error::InvalidTimestamp),
+                "{input:?}"
+            );
+        }
+    }
+
+    const TIMESTAMP: Timestamp = Timestamp {
+        year: 2003,
+        month: 8,
+        day: 24,
+        hour: 5,
+        minute: 14,
+        second: 15,
+        nanos: 3000,
+        offset: -7 * 3600,
+        has_offset: true,
+    };
+
+    #[test]
+    fn chrono() {
+        let got = DateTime::<FixedOffset>::try_from(TIMESTAMP).unwrap();
+        let want = DateTime::parse_from_rfc3339("2003-08-24T05:14:15.000003-07:00").unwrap();
+        assert_eq!(got, want);
+        assert_eq!(got.offset(), want.offset());
+        assert_eq!(Timestamp::from(want), TIMESTAMP);
+
+        let invalid = Timestamp {
+            day: 31,
+            month: 2,
+            ..TIMESTAMP
+        };
+        assert_eq!(
+            DateTime::<FixedOffset>::try_from(invalid),
+            Err(Error::InvalidTimestamp)
+        );
+
+        let invalid = Timestamp {
+            offset: 24 * 3600,
+            ..TIMESTAMP
+        };
+        assert_eq!(
+            DateTime::<FixedOffset>::try_from(invalid),
+            Err(Error::OutOfRangeTimezone)
+        );
+    }
+
+    const LEAP_SECOND: Timestamp = Timestamp {
+        year: 1990,
+        month: 12,
+        day: 31,
+        hour: 23,
+        minute: 59,
+        second: 60,
+        nanos: 0,
+        offset: 0,
+        has_offset: true,
+    };
+
+    #[test]
+    fn chrono_leap_second() {
+        let got = DateTime::<FixedOffset>::try_from(LEAP_SECOND).unwrap();
+        let want = DateTime::parse_from_rfc3339("1990-12-31T23:59:60Z").unwrap();
+        assert_eq!(got, want);
+        assert_eq!(got.timestamp_subsec_nanos(), 1_000_000_000);
+        assert_eq!(Timestamp::from(want), LEAP_SECOND);
+    }
+
+    #[cfg(feature = "time")]
+    #[test]
+    fn time() {
+        let got = time::OffsetDateTime::try_from(TIMESTAMP).unwrap();
+        assert_eq!(got.unix_timestamp_nanos(), 1061727255000003000);
+        assert_eq!(got.offset().whole_seconds(), -7 * 3600);
+
+        let got = time::OffsetDateTime::try_from(LEAP_SECOND).unwrap();
+        assert_eq!(got.unix_timestamp_nanos(), 662688000000000000 - 1);
+    }
+
+    #[cfg(feature = "jiff")]
+    #[test]
+    fn jiff() {
+        let got = jiff::Zoned::try_from(TIMESTAMP).unwrap();
+        assert_eq!(got.timestamp().as_nanosecond(), 1061727255000003000);
+        assert_eq!(got.offset().seconds(), -7 * 3600);
+
+        let got = jiff::Timestamp::try_from(TIMESTAMP).unwrap();
+        assert_eq!(got.as_nanosecond(), 1061727255000003000);
+
+        let got = jiff::Timestamp::try_from(LEAP_SECOND).unwrap();
+        assert_eq!(got.as_nanosecond(), 662688000000000000 - 1);
+    }
+}
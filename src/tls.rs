@@ -0,0 +1,580 @@
+//! Configuration for syslog over TLS, RFC 5425, with rustls.
+//!
+//! A `Connector` configures `sender::Sender::tls`, and an `Acceptor`
+//! `server::Server::tls`. Peers are authenticated by path validation to
+//! trusted root certificates, by certificate fingerprints (RFC 5425 section
+//! 4.2.2), e.g. for self-signed certificates, or both.
+//!
+//! ```no_run
+//! use syslog::sender::Sender;
+//! use syslog::server::Server;
+//! use syslog::tls::pki_types::pem::PemObject;
+//! use syslog::tls::pki_types::{CertificateDer, PrivateKeyDer};
+//! use syslog::tls::{Acceptor, Connector, TLS_PORT};
+//!
+//! let chain = CertificateDer::pem_file_iter("server.pem").unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+//! let key = PrivateKeyDer::from_pem_file("server.key").unwrap();
+//!
+//! // mutual TLS, clients must present a certificate with this fingerprint
+//! let acceptor = Acceptor::new(chain, key).fingerprint(
+//!     "sha-256:E9:2C:26:38:A2:8E:C5:1B:1E:0B:32:C3:D6:1B:40:B3:2F:4A:19:80:23:41:3F:97:C8:4E:F2:C8:38:28:72:E0"
+//!         .parse()
+//!         .unwrap(),
+//! );
+//! let (tx, rx) = std::sync::mpsc::channel();
+//! let listener = Server::new(tx).tls(("0.0.0.0", TLS_PORT), &acceptor).unwrap();
+//!
+//! let roots = CertificateDer::pem_file_iter("ca.pem").unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+//! let connector = Connector::new().root_certificates(roots);
+//! let sender = Sender::tls(("logs.example.com", TLS_PORT), "logs.example.com", &connector).unwrap();
+//! ```
+
+use std::fmt::{self, Display};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::str::FromStr;
+use std::string::String;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::{self, CryptoProvider};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{
+    ClientConfig, ClientConnection, ConnectionCommon, DigitallySignedStruct, DistinguishedName,
+    RootCertStore, ServerConfig, SignatureScheme, StreamOwned,
+};
+
+use crate::Error;
+
+pub use rustls::pki_types;
+
+/// The port of syslog over TLS.
+pub const TLS_PORT: u16 = 6514;
+
+/// How long a handshake may take, before the connection is dropped.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The hash function of a `Fingerprint`, named as in the IANA "Hash Function
+/// Textual Names" registry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl Algorithm {
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "sha-1",
+            Algorithm::Sha256 => "sha-256",
+            Algorithm::Sha384 => "sha-384",
+            Algorithm::Sha512 => "sha-512",
+        }
+    }
+
+    fn digest(self) -> &'static ring::digest::Algorithm {
+        match self {
+            Algorithm::Sha1 => &ring::digest::SHA1_FOR_LEGACY_USE_ONLY,
+            Algorithm::Sha256 => &ring::digest::SHA256,
+            Algorithm::Sha384 => &ring::digest::SHA384,
+            Algorithm::Sha512 => &ring::digest::SHA512,
+        }
+    }
+}
+
+/// The fingerprint of a certificate, the hash of its DER encoding.
+///
+/// The textual form is the hash function's name and the hash in colon
+/// separated hex, e.g. `sha-256:E9:2C:...:E0`. Parsing ignores case, and
+/// also accepts `SHA256`-style names.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    algorithm: Algorithm,
+    hash: Vec<u8>,
+}
+
+impl Fingerprint {
+    /// The fingerprint of the DER encoded certificate `cert`.
+    pub fn of(algorithm: Algorithm, cert: &[u8]) -> Self {
+        Fingerprint {
+            algorithm,
+            hash: ring::digest::digest(algorithm.digest(), cert)
+                .as_ref()
+                .to_vec(),
+        }
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+
+    /// Whether `cert` has this fingerprint.
+    pub fn matches(&self, cert: &[u8]) -> bool {
+        ring::digest::digest(self.algorithm.digest(), cert).as_ref() == self.hash
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, hex) = s.split_once(':').ok_or(Error::InvalidFingerprint)?;
+        let algorithm = match name.to_ascii_lowercase().as_str() {
+            "sha-1" | "sha1" => Algorithm::Sha1,
+            "sha-256" | "sha256" => Algorithm::Sha256,
+            "sha-384" | "sha384" => Algorithm::Sha384,
+            "sha-512" | "sha512" => Algorithm::Sha512,
+            _ => return Err(Error::InvalidFingerprint),
+        };
+
+        let hash = hex
+            .split(':')
+            .map(|byte| match byte.len() {
+                2 => u8::from_str_radix(byte, 16).map_err(|_| Error::InvalidFingerprint),
+                _ => Err(Error::InvalidFingerprint),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if hash.len() != algorithm.digest().output_len() {
+            return Err(Error::InvalidFingerprint);
+        }
+
+        Ok(Fingerprint { algorithm, hash })
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.algorithm.name())?;
+        for byte in &self.hash {
+            write!(f, ":{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The TLS configuration of a sender.
+///
+/// The server's certificate is accepted if it matches one of the
+/// fingerprints, or if it's valid for the server name and issued by one of
+/// the root certificates. Without either, every certificate is rejected.
+#[derive(Debug, Default)]
+pub struct Connector {
+    roots: Vec<CertificateDer<'static>>,
+    fingerprints: Vec<Fingerprint>,
+    identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+}
+
+impl Connector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust certificates issued by `roots`.
+    pub fn root_certificates<I>(mut self, roots: I) -> Self
+    where
+        I: IntoIterator<Item = CertificateDer<'static>>,
+    {
+        self.roots.extend(roots);
+        self
+    }
+
+    /// Trust a certificate with `fingerprint`, whatever its name and issuer.
+    pub fn fingerprint(mut self, fingerprint: Fingerprint) -> Self {
+        self.fingerprints.push(fingerprint);
+        self
+    }
+
+    /// Present `chain`, the certificate first, to servers which authenticate
+    /// their clients.
+    pub fn identity(
+        mut self,
+        chain: Vec<CertificateDer<'static>>,
+        key: PrivateKeyDer<'static>,
+    ) -> Self {
+        self.identity = Some((chain, key));
+        self
+    }
+
+    pub(crate) fn config(&self) -> io::Result<Arc<ClientConfig>> {
+        let provider = provider();
+        let mut webpki = None;
+        if !self.roots.is_empty() {
+            let verifier = WebPkiServerVerifier::builder_with_provider(
+                root_store(&self.roots)?,
+                provider.clone(),
+            )
+            .build()
+            .map_err(invalid_input)?;
+            webpki = Some(verifier);
+        }
+
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(invalid_input)?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(Verifier {
+                fingerprints: self.fingerprints.clone(),
+                webpki,
+                provider,
+            }));
+
+        let config = match &self.identity {
+            Some((chain, key)) => builder
+                .with_client_auth_cert(chain.clone(), key.clone_key())
+                .map_err(invalid_input)?,
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(config))
+    }
+}
+
+/// The TLS configuration of a listener.
+///
+/// Clients are only authenticated if root certificates or fingerprints are
+/// configured, and must present a certificate then. It's accepted if it
+/// matches one of the fingerprints, or if it's issued by one of the root
+/// certificates.
+#[derive(Debug)]
+pub struct Acceptor {
+    chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    roots: Vec<CertificateDer<'static>>,
+    fingerprints: Vec<Fingerprint>,
+}
+
+impl Acceptor {
+    /// Present `chain`, the certificate first, to clients.
+    pub fn new(chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> Self {
+        Acceptor {
+            chain,
+            key,
+            roots: Vec::new(),
+            fingerprints: Vec::new(),
+        }
+    }
+
+    /// Accept client certificates issued by `roots`.
+    pub fn root_certificates<I>(mut self, roots: I) -> Self
+    where
+        I: IntoIterator<Item = CertificateDer<'static>>,
+    {
+        self.roots.extend(roots);
+        self
+    }
+
+    /// Accept a client certificate with `fingerprint`, whatever its issuer.
+    pub fn fingerprint(mut self, fingerprint: Fingerprint) -> Self {
+        self.fingerprints.push(fingerprint);
+        self
+    }
+
+    pub(crate) fn config(&self) -> io::Result<Arc<ServerConfig>> {
+        let provider = provider();
+        let builder = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(invalid_input)?;
+
+        let builder = if self.roots.is_empty() && self.fingerprints.is_empty() {
+            builder.with_no_client_auth()
+        } else {
+            let mut webpki = None;
+            if !self.roots.is_empty() {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    root_store(&self.roots)?,
+                    provider.clone(),
+                )
+                .build()
+                .map_err(invalid_input)?;
+                webpki = Some(verifier);
+            }
+
+            builder.with_client_cert_verifier(Arc::new(Verifier {
+                fingerprints: self.fingerprints.clone(),
+                webpki,
+                provider,
+            }))
+        };
+
+        let config = builder
+            .with_single_cert(self.chain.clone(), self.key.clone_key())
+            .map_err(invalid_input)?;
+
+        Ok(Arc::new(config))
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(crypto::ring::default_provider())
+}
+
+fn root_store(roots: &[CertificateDer<'static>]) -> io::Result<Arc<RootCertStore>> {
+    let mut store = RootCertStore::empty();
+    for root in roots {
+        store.add(root.clone()).map_err(invalid_input)?;
+    }
+
+    Ok(Arc::new(store))
+}
+
+fn invalid_input<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, err)
+}
+
+/// Accepts certificates with one of the fingerprints, and delegates to
+/// path validation otherwise.
+#[derive(Debug)]
+struct Verifier<V: ?Sized> {
+    fingerprints: Vec<Fingerprint>,
+    webpki: Option<Arc<V>>,
+    provider: Arc<CryptoProvider>,
+}
+
+impl<V: ?Sized> Verifier<V> {
+    fn matches(&self, cert: &CertificateDer<'_>) -> bool {
+        self.fingerprints.iter().any(|fp| fp.matches(cert))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+fn unknown_certificate() -> rustls::Error {
+    rustls::Error::InvalidCertificate(rustls::CertificateError::ApplicationVerificationFailure)
+}
+
+impl ServerCertVerifier for Verifier<WebPkiServerVerifier> {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if self.matches(end_entity) {
+            return Ok(ServerCertVerified::assertion());
+        }
+
+        match &self.webpki {
+            Some(webpki) => webpki.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                ocsp_response,
+                now,
+            ),
+            None => Err(unknown_certificate()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Verifier::verify_tls12_signature(self, message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Verifier::verify_tls13_signature(self, message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        Verifier::supported_verify_schemes(self)
+    }
+}
+
+impl ClientCertVerifier for Verifier<dyn ClientCertVerifier> {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        match &self.webpki {
+            Some(webpki) => webpki.root_hint_subjects(),
+            None => &[],
+        }
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        if self.matches(end_entity) {
+            return Ok(ClientCertVerified::assertion());
+        }
+
+        match &self.webpki {
+            Some(webpki) => webpki.verify_client_cert(end_entity, intermediates, now),
+            None => Err(unknown_certificate()),
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Verifier::verify_tls12_signature(self, message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Verifier::verify_tls13_signature(self, message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        Verifier::supported_verify_schemes(self)
+    }
+}
+
+/// Connect to `addrs` and complete the handshake, so certificate errors are
+/// reported right away.
+pub(crate) fn connect(
+    addrs: &[SocketAddr],
+    server_name: &ServerName<'static>,
+    config: &Arc<ClientConfig>,
+) -> io::Result<StreamOwned<ClientConnection, TcpStream>> {
+    let sock = TcpStream::connect(addrs)?;
+    sock.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let conn =
+        ClientConnection::new(config.clone(), server_name.clone()).map_err(io::Error::other)?;
+
+    let mut stream = StreamOwned::new(conn, sock);
+    handshake(&mut stream, HANDSHAKE_TIMEOUT, || false)?;
+    stream.sock.set_read_timeout(None)?;
+
+    Ok(stream)
+}
+
+/// Drive the handshake of `stream`, retrying reads which time out unless
+/// `stop` returns true. Fails with `TimedOut` once `timeout` has passed, even
+/// if the peer keeps sending.
+pub(crate) fn handshake<C, S, D>(
+    stream: &mut StreamOwned<C, S>,
+    timeout: Duration,
+    stop: impl Fn() -> bool,
+) -> io::Result<()>
+where
+    C: std::ops::DerefMut<Target = ConnectionCommon<D>>,
+    S: Read + Write,
+    D: rustls::SideData,
+{
+    let deadline = Instant::now() + timeout;
+    while stream.conn.is_handshaking() {
+        if Instant::now() >= deadline {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+
+        match stream.conn.complete_io(&mut stream.sock) {
+            Ok(_) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) && !stop() => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// The subject of a DER encoded certificate, e.g. `CN=web01, O=Example`.
+pub(crate) fn subject(cert: &[u8]) -> Option<String> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert).ok()?;
+    Some(cert.subject().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprints() {
+        let fp = Fingerprint::of(Algorithm::Sha256, b"certificate");
+        let text = fp.to_string();
+        assert!(text.starts_with("sha-256:"));
+        assert_eq!(text.len(), "sha-256".len() + 32 * 3);
+        assert_eq!(text.parse(), Ok(fp.clone()));
+        assert_eq!(
+            text.to_lowercase().replace("sha-256", "SHA256").parse(),
+            Ok(fp.clone())
+        );
+        assert!(fp.matches(b"certificate"));
+        assert!(!fp.matches(b"other"));
+
+        let sha1 = "SHA-1:2E:CA:0D:9C:46:B3:5C:F1:A7:E1:4D:B2:3E:CF:F0:A7:05:3D:43:3B"
+            .parse::<Fingerprint>()
+            .unwrap();
+        assert_eq!(sha1.algorithm(), Algorithm::Sha1);
+        assert_eq!(sha1.hash()[..2], [0x2e, 0xca]);
+
+        for invalid in [
+            "",
+            "sha-256",
+            "md5:00:11",
+            "sha-1:2E:CA",
+            "sha-1:2ECA0D9C46B35CF1A7E14DB23ECFF0A7053D433B",
+            "sha-1:2E:CA:0D:9C:46:B3:5C:F1:A7:E1:4D:B2:3E:CF:F0:A7:05:3D:43:XX",
+        ] {
+            assert_eq!(
+                invalid.parse::<Fingerprint>(),
+                Err(Error::InvalidFingerprint),
+                "{invalid}"
+            );
+        }
+    }
+}
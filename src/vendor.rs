@@ -0,0 +1,226 @@
+//! Optional post-parsers for vendor specific conventions, which are not
+//! covered by the RFCs.
+//!
+//! ```
+//! use syslog::vendor::{parse_tag, split_logical_system};
+//! use syslog::Severity;
+//!
+//! let raw = b"<28>1 2020-05-22T14:59:09.250-03:00 OX-XXX-MX204 OX-XXX-CONTEUDO:rpd 6589 - - bgp_listen_accept: %DAEMON-4: Connection attempt";
+//! let msg = syslog::rfc5424::parse_message(raw).unwrap();
+//!
+//! let app = split_logical_system(msg.appname.unwrap());
+//! assert_eq!(app.logical_system, Some("OX-XXX-CONTEUDO"));
+//! assert_eq!(app.process, "rpd");
+//!
+//! let tag = parse_tag(msg.msg).unwrap();
+//! assert_eq!(tag.facility, "DAEMON");
+//! assert_eq!(tag.severity, Severity::WARNING);
+//! assert_eq!(tag.text, "Connection attempt");
+//! ```
+
+use crate::Severity;
+
+/// The APP-NAME of Juniper routers, see `split_logical_system`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JuniperApp<'a> {
+    /// The logical system (LSYS) the process runs in, `None` for the
+    /// default one.
+    pub logical_system: Option<&'a str>,
+    pub process: &'a str,
+}
+
+/// Split a Juniper APP-NAME like `OX-XXX-CONTEUDO:rpd` into the logical
+/// system and the process name. Names without a `:` belong to the default
+/// logical system.
+pub fn split_logical_system(appname: &str) -> JuniperApp<'_> {
+    match appname.rsplit_once(':') {
+        Some((logical_system, process)) if !logical_system.is_empty() => JuniperApp {
+            logical_system: Some(logical_system),
+            process,
+        },
+        _ => JuniperApp {
+            logical_system: None,
+            process: appname,
+        },
+    }
+}
+
+/// A `%FACILITY-SEVERITY-MNEMONIC:` message tag, as sent by Cisco and Juniper
+/// devices, see `parse_tag`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tag<'a> {
+    /// Everything before the tag, without trailing whitespace, e.g. the
+    /// sequence number and timestamp of Cisco IOS, or the function name of
+    /// Juniper daemons.
+    pub prefix: &'a str,
+    /// The facility, including the sub-facility if any, e.g. `LINK` or
+    /// `C4K_EBM`. This is the vendor's facility, not `Facility`.
+    pub facility: &'a str,
+    pub severity: Severity,
+    /// e.g. `UPDOWN`, Juniper tags often have none.
+    pub mnemonic: Option<&'a str>,
+    /// The message after the tag.
+    pub text: &'a str,
+}
+
+/// Find the first `%FACILITY-SEVERITY[-MNEMONIC]:` tag in `msg`.
+///
+/// The tag must start the message or follow a space, the facility and
+/// mnemonic consist of ASCII letters, digits and underscores, and the
+/// severity is a single digit `0..=7`. Cisco's `%FACILITY-SUBFACILITY-...`
+/// form is supported as well, the sub-facility is kept in `facility`.
+pub fn parse_tag(msg: &str) -> Option<Tag<'_>> {
+    let bytes = msg.as_bytes();
+    let mut start = 0;
+
+    while let Some(pos) = bytes[start..].iter().position(|ch| *ch == b'%') {
+        let pos = start + pos;
+        start = pos + 1;
+
+        if pos > 0 && bytes[pos - 1] != b' ' {
+            continue;
+        }
+
+        let len = bytes[start..].iter().position(|ch| *ch == b':')?;
+
+        if let Some((facility, severity, mnemonic)) = split_tag(&msg[start..start + len]) {
+            return Some(Tag {
+                prefix: msg[..pos].trim_end(),
+                facility,
+                severity,
+                mnemonic,
+                text: msg[start + len + 1..].trim_start(),
+            });
+        }
+    }
+
+    None
+}
+
+fn split_tag(tag: &str) -> Option<(&str, Severity, Option<&str>)> {
+    let valid = |part: &str| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == b'_')
+    };
+
+    // the severity is the first part which is a single digit, after the
+    // facility
+    let mut offset = 0;
+    for part in tag.split('-') {
+        if offset > 0 && part.len() == 1 && part.as_bytes()[0].is_ascii_digit() {
+            let severity = Severity::try_from((part.as_bytes()[0] - b'0') as i32).ok()?;
+            let facility = &tag[..offset - 1];
+            let mnemonic = tag.get(offset + 2..);
+
+            if !facility.split('-').all(valid) || !mnemonic.is_none_or(valid) {
+                return None;
+            }
+
+            return Some((facility, severity, mnemonic));
+        }
+
+        offset += part.len() + 1;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_system() {
+        for (input, logical_system, process) in [
+            ("OX-XXX-CONTEUDO:rpd", Some("OX-XXX-CONTEUDO"), "rpd"),
+            ("rpd", None, "rpd"),
+            (":rpd", None, ":rpd"),
+        ] {
+            assert_eq!(
+                split_logical_system(input),
+                JuniperApp {
+                    logical_system,
+                    process
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn tags() {
+        for (input, want) in [
+            (
+                "%LINK-3-UPDOWN: Interface GigabitEthernet0/1, changed state to down",
+                Tag {
+                    prefix: "",
+                    facility: "LINK",
+                    severity: Severity::ERR,
+                    mnemonic: Some("UPDOWN"),
+                    text: "Interface GigabitEthernet0/1, changed state to down",
+                },
+            ),
+            (
+                "bgp_listen_accept: %DAEMON-4: Connection attempt from unconfigured neighbor: 2001:XXX::219:166+57284",
+                Tag {
+                    prefix: "bgp_listen_accept:",
+                    facility: "DAEMON",
+                    severity: Severity::WARNING,
+                    mnemonic: None,
+                    text: "Connection attempt from unconfigured neighbor: 2001:XXX::219:166+57284",
+                },
+            ),
+            (
+                "000123: *Mar  1 00:00:01.123: %SYS-5-CONFIG_I: Configured from console",
+                Tag {
+                    prefix: "000123: *Mar  1 00:00:01.123:",
+                    facility: "SYS",
+                    severity: Severity::NOTICE,
+                    mnemonic: Some("CONFIG_I"),
+                    text: "Configured from console",
+                },
+            ),
+            (
+                "%C4K_EBM-HOST-4-HOSTFLAPPING: Host 00:11:22:33:44:55 is flapping",
+                Tag {
+                    prefix: "",
+                    facility: "C4K_EBM-HOST",
+                    severity: Severity::WARNING,
+                    mnemonic: Some("HOSTFLAPPING"),
+                    text: "Host 00:11:22:33:44:55 is flapping",
+                },
+            ),
+            (
+                "CPU at 100% %ASA-6-302013: Built outbound TCP connection",
+                Tag {
+                    prefix: "CPU at 100%",
+                    facility: "ASA",
+                    severity: Severity::INFO,
+                    mnemonic: Some("302013"),
+                    text: "Built outbound TCP connection",
+                },
+            ),
+        ] {
+            assert_eq!(parse_tag(input), Some(want), "{input}");
+        }
+    }
+
+    #[test]
+    fn no_tags() {
+        for input in [
+            "",
+            "bananas and peas",
+            "load is 100%: too high",
+            "%LINK-UPDOWN: missing severity",
+            "%LINK-9-UPDOWN: out of range",
+            "%-3-UPDOWN: no facility",
+            "%LINK-3-: empty mnemonic",
+            "%LINK-3-UP DOWN: space",
+            "foo%LINK-3-UPDOWN: not after space",
+            "%LINK-3-UPDOWN",
+        ] {
+            assert_eq!(parse_tag(input), None, "{input}");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use syslog::rfc5424::parse_message_in;
+use syslog::Scratch;
+
+struct Counting;
+
+// Only allocations of the measuring thread count, the test harness and other
+// tests allocate concurrently.
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if COUNTING.try_with(Cell::get).unwrap_or(false) {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        }
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+#[test]
+fn parse_with_scratch_does_not_allocate() {
+    let inputs = [
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Application" eventID="1011"][examplePriority@32473 class="high"] BOMAn application event log entry..."#,
+        r#"<13>1 2019-02-13T19:48:34+00:00 74794bfb6795 root 8449 - [meta sequenceId="1" sysUpTime="37" language="EN"][origin ip="192.168.0.1" software="test"] i am foobar"#,
+        r#"<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - BOM'su root' failed for lonvick on /dev/pts/8"#,
+    ];
+
+    // warm up, so the scratch grows big enough for all inputs
+    let mut scratch = Scratch::new();
+    for input in inputs {
+        parse_message_in(input.as_bytes(), &mut scratch).unwrap();
+    }
+
+    COUNTING.with(|counting| counting.set(true));
+    let mut params = 0;
+    for _ in 0..100 {
+        for input in inputs {
+            let msg = parse_message_in(input.as_bytes(), &mut scratch).unwrap();
+            params += msg
+                .structured_data
+                .iter()
+                .map(|element| element.params().count())
+                .sum::<usize>();
+        }
+    }
+
+    COUNTING.with(|counting| counting.set(false));
+
+    assert_eq!(ALLOCATIONS.with(Cell::get), 0);
+    assert_eq!(params, 100 * 9);
+}
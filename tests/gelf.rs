@@ -0,0 +1,68 @@
+#![cfg(feature = "gelf")]
+
+use std::io::Write;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use syslog::gelf::{chunks, decode, encode, Reassembler};
+use syslog::rfc5424::parse_message;
+
+#[test]
+fn chunked_udp_loopback() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    receiver
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+    sender.connect(receiver.local_addr().unwrap()).unwrap();
+
+    let text = "a long message ".repeat(200);
+    let raw = format!(
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3"] {text}"#
+    );
+    let msg = parse_message(raw.as_bytes()).unwrap();
+
+    // random bytes don't compress, so the message needs several chunks even
+    // after gzip
+    let mut payload = encode(&msg);
+    payload.pop();
+    payload.extend_from_slice(br#","_noise":""#);
+    let mut state = 0x2545f491u32;
+    for _ in 0..4000 {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        payload.push(b'a' + (state % 26) as u8);
+    }
+    payload.extend_from_slice(br#""}"#);
+
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    gzip.write_all(&payload).unwrap();
+    let gzip = gzip.finish().unwrap();
+
+    let datagrams = chunks(&gzip, 1420, *b"\x01\x02\x03\x04\x05\x06\x07\x08").unwrap();
+    assert!(datagrams.len() > 1);
+    for datagram in datagrams.iter().rev() {
+        sender.send(datagram).unwrap();
+    }
+    // a plain datagram is passed through
+    sender.send(&encode(&msg)).unwrap();
+
+    let mut reassembler = Reassembler::default();
+    let mut buf = [0u8; 8192];
+    let mut decoded = vec![];
+    while decoded.len() < 2 {
+        let n = receiver.recv(&mut buf).unwrap();
+        if let Some(payload) = reassembler.push(&buf[..n], Instant::now()).unwrap() {
+            decoded.push(decode(&payload).unwrap());
+        }
+    }
+
+    for got in decoded {
+        assert_eq!(got.msg, text);
+        assert_eq!(got.hostname.as_deref(), Some("mymachine.example.com"));
+        assert_eq!(got.timestamp, msg.timestamp);
+    }
+}
@@ -1,6 +1,6 @@
 use chrono::{Duration, FixedOffset, TimeZone};
 use syslog::rfc5424::parse_message;
-use syslog::{Facility, Message, ProcId, Protocol, Severity, StructuredElement};
+use syslog::{Facility, Host, Message, ProcId, Protocol, Severity, StructuredElement};
 
 #[test]
 fn parse_5424_no_structured_data() {
@@ -403,7 +403,10 @@ fn parse_ipv4_hostname() {
             msg: "bananas and peas",
         },
         parse_message(msg.as_bytes()).unwrap()
-    )
+    );
+
+    let msg = parse_message(msg.as_bytes()).unwrap();
+    assert_eq!(msg.host(), Some(Ok(Host::Ip("42.52.1.1".parse().unwrap()))));
 }
 
 #[test]
@@ -429,5 +432,33 @@ fn parse_ipv6_hostname() {
             msg: "bananas and peas",
         },
         parse_message(msg.as_bytes()).unwrap()
-    )
+    );
+
+    let msg = parse_message(msg.as_bytes()).unwrap();
+    assert_eq!(
+        msg.host(),
+        Some(Ok(Host::Ip("::FFFF:129.144.52.38".parse().unwrap())))
+    );
+}
+
+#[test]
+fn classify_hostname() {
+    for (input, want) in [
+        (
+            "<34>1 - mymachine.example.com su - - - hi",
+            Some(Ok(Host::Fqdn("mymachine.example.com"))),
+        ),
+        (
+            "<34>1 - mymachine su - - - hi",
+            Some(Ok(Host::Simple("mymachine"))),
+        ),
+        (
+            "<34>1 - my_machine su - - - hi",
+            Some(Err(syslog::Error::InvalidHostname)),
+        ),
+        ("<34>1 - - su - - - hi", None),
+    ] {
+        let msg = parse_message(input.as_bytes()).unwrap();
+        assert_eq!(msg.host(), want, "{input}");
+    }
 }
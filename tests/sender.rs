@@ -0,0 +1,195 @@
+#![cfg(feature = "std")]
+
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, UdpSocket};
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use syslog::rfc5424::parse_message;
+use syslog::sender::{Format, Sender, UDP_MIN_LEN_IPV4};
+use syslog::stream::Messages;
+use syslog::{Message, ProcId};
+
+const RAW: &str = r#"<165>1 2003-10-11T22:14:15.003000Z mymachine.example.com evntslog 8710 ID47 [exampleSDID@32473 iut="3"] An application event log entry"#;
+
+fn message() -> Message<&'static str> {
+    parse_message(RAW.as_bytes()).unwrap()
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("syslog-{}-{name}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// Read an octet-counted frame as it was sent, without parsing it.
+fn read_frame<R: BufRead>(reader: &mut R) -> String {
+    let mut len = Vec::new();
+    reader.read_until(b' ', &mut len).unwrap();
+    let len = std::str::from_utf8(&len)
+        .unwrap()
+        .trim_end()
+        .parse()
+        .unwrap();
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).unwrap();
+    String::from_utf8(frame).unwrap()
+}
+
+#[test]
+fn udp() {
+    let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut sender = Sender::udp(receiver.local_addr().unwrap()).unwrap();
+    let mut buf = [0u8; 4096];
+
+    sender.send(&message()).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], RAW.as_bytes());
+
+    let mut msg = message();
+    let text = "é".repeat(1000);
+    msg.msg = &text;
+    sender = sender.max_len(UDP_MIN_LEN_IPV4);
+    sender.send(&msg).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert!((UDP_MIN_LEN_IPV4 - 1..=UDP_MIN_LEN_IPV4).contains(&len));
+    let got = parse_message(&buf[..len]).unwrap();
+    assert!(text.starts_with(got.msg));
+
+    let mut sender = Sender::udp(receiver.local_addr().unwrap())
+        .unwrap()
+        .format(Format::Rfc3164);
+    sender.send(&message()).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(
+        &buf[..len],
+        b"<165>Oct 11 22:14:15 mymachine.example.com evntslog[8710]: An application event log entry"
+    );
+}
+
+#[test]
+fn tcp_reconnect() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut sender = Sender::tcp(listener.local_addr().unwrap()).unwrap();
+
+    let mut msg = message();
+    msg.msg = "multi\nline";
+    sender.send(&msg).unwrap();
+    sender.send(&message()).unwrap();
+
+    let (conn, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(conn);
+    assert!(read_frame(&mut reader).ends_with(" multi\nline"));
+    assert_eq!(read_frame(&mut reader), RAW);
+    drop(reader);
+
+    // writes to the closed connection may still succeed, until the reset
+    // arrives
+    listener.set_nonblocking(true).unwrap();
+    let mut conn = None;
+    for _ in 0..100 {
+        sender.send(&message()).unwrap();
+        if let Ok((accepted, _)) = listener.accept() {
+            conn = Some(accepted);
+            break;
+        }
+        sleep(Duration::from_millis(20));
+    }
+
+    let conn = conn.expect("the sender reconnects");
+    conn.set_nonblocking(false).unwrap();
+    assert_eq!(read_frame(&mut BufReader::new(conn)), RAW);
+}
+
+#[test]
+fn write_timeout() {
+    // the server accepts connections, but never reads from them
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let mut sender = Sender::tcp(listener.local_addr().unwrap())
+        .unwrap()
+        .write_timeout(Some(Duration::from_millis(100)));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let text = "x".repeat(64 * 1024);
+    std::thread::spawn(move || {
+        let mut msg = message();
+        msg.msg = &text;
+        loop {
+            let result = sender.send(&msg);
+            if tx.send(result.is_ok()).is_err() {
+                return;
+            }
+        }
+    });
+
+    // the sender gives up on the full connection and connects again,
+    // instead of blocking
+    let mut connections = vec![];
+    while connections.len() < 2 {
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("send returns");
+        while let Ok((conn, _)) = listener.accept() {
+            connections.push(conn);
+        }
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_datagram() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path = socket_path("datagram");
+    let receiver = UnixDatagram::bind(&path).unwrap();
+    let mut sender = Sender::unix(&path).unwrap();
+
+    let mut buf = [0u8; 4096];
+    sender.send(&message()).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..len], RAW.as_bytes());
+
+    // the syslog daemon restarts
+    drop(receiver);
+    std::fs::remove_file(&path).unwrap();
+    let receiver = UnixDatagram::bind(&path).unwrap();
+
+    let mut msg = message();
+    msg.procid = Some(ProcId::PID(1));
+    sender.send(&msg).unwrap();
+    let len = receiver.recv(&mut buf).unwrap();
+    assert_eq!(parse_message(&buf[..len]).unwrap(), msg);
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_stream() {
+    use std::os::unix::net::UnixListener;
+
+    let path = socket_path("stream");
+    let listener = UnixListener::bind(&path).unwrap();
+    let mut sender = Sender::unix(&path).unwrap();
+
+    sender.send(&message()).unwrap();
+    sender.send(&message()).unwrap();
+    drop(sender);
+
+    let (conn, _) = listener.accept().unwrap();
+    let mut count = 0;
+    Messages::new(BufReader::new(conn))
+        .for_each(
+            |msg| {
+                assert_eq!(msg, message());
+                count += 1;
+            },
+            |line, err| panic!("line {line}: {err}"),
+        )
+        .unwrap();
+    assert_eq!(count, 2);
+
+    std::fs::remove_file(&path).unwrap();
+}
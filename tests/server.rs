@@ -0,0 +1,334 @@
+#![cfg(feature = "std")]
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, UdpSocket};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use syslog::rfc5424::parse_message;
+use syslog::sender::{Format, Framing, Sender};
+use syslog::server::{Handler, Peer, Received, Server};
+use syslog::{Error, Message, Protocol};
+
+const RAW: &str = r#"<165>1 2003-10-11T22:14:15.003000Z mymachine.example.com evntslog 8710 ID47 [exampleSDID@32473 iut="3"] An application event log entry"#;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn message() -> Message<String> {
+    parse_message(RAW.as_bytes()).unwrap().into_owned()
+}
+
+fn socket_path(name: &str) -> PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("syslog-server-{}-{name}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn recv(rx: &Receiver<Received>) -> Received {
+    rx.recv_timeout(TIMEOUT).expect("a message")
+}
+
+/// Collects messages and errors.
+#[derive(Clone, Default)]
+struct Collect {
+    messages: Arc<Mutex<Vec<Message<String>>>>,
+    errors: Arc<Mutex<Vec<Error>>>,
+}
+
+impl Handler for Collect {
+    fn handle(&self, received: Received) {
+        self.messages.lock().unwrap().push(received.message);
+    }
+
+    fn error(&self, _: &Peer, err: Error) {
+        self.errors.lock().unwrap().push(err);
+    }
+}
+
+impl Collect {
+    fn wait(&self, messages: usize, errors: usize) {
+        for _ in 0..500 {
+            if self.messages.lock().unwrap().len() >= messages
+                && self.errors.lock().unwrap().len() >= errors
+            {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("timed out");
+    }
+}
+
+#[test]
+fn udp() {
+    let (tx, rx) = mpsc::channel();
+    let listener = Server::new(tx).udp("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.send_to(RAW.as_bytes(), addr).unwrap();
+    socket.send_to(format!("{RAW}\n").as_bytes(), addr).unwrap();
+
+    for _ in 0..2 {
+        let received = recv(&rx);
+        assert_eq!(received.message, message());
+        assert_eq!(received.peer, Peer::Udp(socket.local_addr().unwrap()));
+    }
+
+    let mut sender = Sender::udp(addr).unwrap();
+    sender.send(&message()).unwrap();
+    assert_eq!(recv(&rx).message, message());
+
+    listener.shutdown();
+}
+
+#[test]
+fn tcp() {
+    let (tx, rx) = mpsc::channel();
+    let listener = Server::new(tx).tcp("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // octet-counting and non-transparent framing on the same connection, in
+    // pieces
+    let mut conn = TcpStream::connect(addr).unwrap();
+    let framed = format!("{} {RAW}{RAW}\r\n{RAW}", RAW.len());
+    for piece in framed.as_bytes().chunks(50) {
+        conn.write_all(piece).unwrap();
+        conn.flush().unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    drop(conn);
+
+    for _ in 0..3 {
+        let received = recv(&rx);
+        assert_eq!(received.message, message());
+        assert!(matches!(received.peer, Peer::Tcp(peer) if peer.ip() == addr.ip()));
+    }
+
+    let mut sender = Sender::tcp(addr).unwrap();
+    let mut multi = message();
+    multi.msg = "multi\nline".into();
+    sender.send(&multi).unwrap();
+    sender.send(&message()).unwrap();
+    assert_eq!(recv(&rx).message, multi);
+    assert_eq!(recv(&rx).message, message());
+
+    listener.shutdown();
+}
+
+#[test]
+fn max_len() {
+    let collect = Collect::default();
+    let server = Server::new(collect.clone()).max_len(RAW.len());
+    let udp = server.udp("127.0.0.1:0").unwrap();
+    let tcp = server.tcp("127.0.0.1:0").unwrap();
+
+    let long = format!("{RAW}!");
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .send_to(long.as_bytes(), udp.local_addr().unwrap())
+        .unwrap();
+    socket
+        .send_to(RAW.as_bytes(), udp.local_addr().unwrap())
+        .unwrap();
+    collect.wait(1, 1);
+
+    // the connection is closed after a frame which is too long
+    let mut conn = TcpStream::connect(tcp.local_addr().unwrap()).unwrap();
+    write!(conn, "{} {RAW}{} {long}", RAW.len(), long.len()).unwrap();
+    collect.wait(2, 2);
+
+    drop((udp, tcp));
+    assert_eq!(*collect.messages.lock().unwrap(), [message(), message()]);
+    assert_eq!(
+        *collect.errors.lock().unwrap(),
+        [Error::MessageTooLong, Error::MessageTooLong]
+    );
+}
+
+#[test]
+fn auto_detect() {
+    let (tx, rx) = mpsc::channel();
+    let collect = Collect::default();
+    let strict = Server::new(collect.clone()).udp("127.0.0.1:0").unwrap();
+    let detect = Server::new(tx)
+        .auto_detect(true)
+        .udp("127.0.0.1:0")
+        .unwrap();
+
+    let bsd = Message {
+        protocol: Protocol::RFC3164,
+        ..message()
+    };
+    for listener in [&strict, &detect] {
+        let mut sender = Sender::udp(listener.local_addr().unwrap())
+            .unwrap()
+            .format(Format::Rfc3164);
+        sender.send(&bsd).unwrap();
+        let mut sender = Sender::udp(listener.local_addr().unwrap()).unwrap();
+        sender.send(&message()).unwrap();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket
+            .send_to(b"<13>\xff invalid", listener.local_addr().unwrap())
+            .unwrap();
+    }
+
+    let got = recv(&rx).message;
+    assert_eq!(got.protocol, Protocol::RFC3164);
+    assert_eq!(got.hostname.as_deref(), Some("mymachine.example.com"));
+    assert_eq!(got.appname.as_deref(), Some("evntslog"));
+    assert_eq!(got.msg, "An application event log entry");
+    assert_eq!(recv(&rx).message, message());
+
+    collect.wait(1, 2);
+    drop(strict);
+    assert_eq!(*collect.messages.lock().unwrap(), [message()]);
+    assert_eq!(collect.errors.lock().unwrap()[1], Error::InvalidUtf8);
+}
+
+#[test]
+fn shutdown() {
+    let collect = Collect::default();
+    let server = Server::new(collect.clone());
+    let listener = server.tcp("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // an idle connection doesn't keep the listener alive, and complete frames
+    // are delivered
+    let mut conn = TcpStream::connect(addr).unwrap();
+    write!(conn, "{} {RAW}", RAW.len()).unwrap();
+    collect.wait(1, 0);
+    listener.shutdown();
+
+    assert!(TcpStream::connect(addr).is_err());
+    assert_eq!(collect.messages.lock().unwrap().len(), 1);
+
+    // the address can be bound again
+    let listener = server.tcp(addr).unwrap();
+    drop(listener);
+}
+
+#[test]
+fn max_connections() {
+    let (tx, rx) = mpsc::channel();
+    let listener = Server::new(tx)
+        .max_connections(1)
+        .tcp("127.0.0.1:0")
+        .unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut first = TcpStream::connect(addr).unwrap();
+    write!(first, "{} {RAW}", RAW.len()).unwrap();
+    recv(&rx);
+
+    // the second connection is closed without reading from it
+    let mut second = TcpStream::connect(addr).unwrap();
+    second.set_read_timeout(Some(TIMEOUT)).unwrap();
+    let _ = write!(second, "{} {RAW}", RAW.len());
+    assert!(matches!(second.read(&mut [0; 1]), Ok(0) | Err(_)));
+    assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+    // until the first one is gone
+    drop(first);
+    std::thread::sleep(Duration::from_millis(300));
+    let mut third = TcpStream::connect(addr).unwrap();
+    write!(third, "{} {RAW}", RAW.len()).unwrap();
+    assert_eq!(recv(&rx).message, message());
+}
+
+#[test]
+fn idle_timeout() {
+    let (tx, rx) = mpsc::channel();
+    let timeout = Duration::from_millis(300);
+    let listener = Server::new(tx)
+        .idle_timeout(timeout)
+        .tcp("127.0.0.1:0")
+        .unwrap();
+
+    let mut conn = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    conn.set_read_timeout(Some(TIMEOUT)).unwrap();
+    write!(conn, "{} {RAW}", RAW.len()).unwrap();
+    recv(&rx);
+
+    // an incomplete frame doesn't keep the connection open
+    let start = Instant::now();
+    write!(conn, "{} <13>1", RAW.len()).unwrap();
+    assert!(matches!(conn.read(&mut [0; 1]), Ok(0) | Err(_)));
+    assert!(start.elapsed() < TIMEOUT);
+    assert!(rx.try_recv().is_err());
+}
+
+#[test]
+fn handler_closure() {
+    let (tx, rx) = mpsc::sync_channel(1);
+    let tx = Mutex::new(tx);
+    let listener = Server::new(move |received: Received| {
+        tx.lock().unwrap().send(received.message.msg).unwrap();
+    })
+    .udp("127.0.0.1:0")
+    .unwrap();
+
+    let mut sender = Sender::udp(listener.local_addr().unwrap()).unwrap();
+    sender.send(&message()).unwrap();
+    assert_eq!(
+        rx.recv_timeout(TIMEOUT).unwrap(),
+        "An application event log entry"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_datagram() {
+    let (tx, rx) = mpsc::channel();
+    let path = socket_path("datagram");
+    let listener = Server::new(tx).unix_datagram(&path).unwrap();
+
+    let mut sender = Sender::unix(&path).unwrap();
+    sender.send(&message()).unwrap();
+    let received = recv(&rx);
+    assert_eq!(received.message, message());
+    assert_eq!(received.peer, Peer::Unix(None));
+
+    listener.shutdown();
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_stream() {
+    use std::os::unix::net::UnixStream;
+
+    let (tx, rx) = mpsc::channel();
+    let path = socket_path("stream");
+    let listener = Server::new(tx).unix_stream(&path).unwrap();
+
+    let mut sender = Sender::unix(&path).unwrap();
+    sender.send(&message()).unwrap();
+    assert_eq!(recv(&rx).message, message());
+
+    // newlines would split the message with non-transparent framing
+    let mut multi = message();
+    multi.msg = "multi\nline".into();
+    sender.send(&multi).unwrap();
+    assert_eq!(recv(&rx).message.msg, "multi line");
+
+    let mut sender = Sender::unix_stream(&path)
+        .unwrap()
+        .framing(Framing::OctetCounting);
+    sender.send(&message()).unwrap();
+    assert_eq!(recv(&rx).message, message());
+
+    // NUL terminated, like syslog(3)
+    let mut conn = UnixStream::connect(&path).unwrap();
+    write!(conn, "{RAW}\0{RAW}\0").unwrap();
+    assert_eq!(recv(&rx).message, message());
+    assert_eq!(recv(&rx).message, message());
+
+    drop((conn, sender));
+    listener.shutdown();
+    assert!(!path.exists());
+}
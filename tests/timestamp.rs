@@ -0,0 +1,40 @@
+use chrono::DateTime;
+use proptest::prelude::*;
+use syslog::rfc5424::parse_timestamp;
+
+fn check(input: &str) {
+    let got = parse_timestamp(input.as_bytes(), &mut 0);
+    let want = DateTime::parse_from_rfc3339(input);
+
+    match (got, want) {
+        (Ok(got), Ok(want)) => {
+            assert_eq!(got, want, "input: {input}");
+            assert_eq!(got.offset(), want.offset(), "input: {input}");
+        }
+        (Err(_), Err(_)) => {}
+        (got, want) => panic!("input: {input}, got: {got:?}, want: {want:?}"),
+    }
+}
+
+proptest! {
+    #[test]
+    fn valid_timestamps(
+        input in "[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|[12][0-9]|3[01])[Tt]([01][0-9]|2[0-3]):[0-5][0-9]:([0-5][0-9]|60)(\\.[0-9]{1,12})?([Zz]|[+-]([01][0-9]|2[0-3]):[0-5][0-9])"
+    ) {
+        check(&input);
+    }
+
+    #[test]
+    fn random_digits(
+        input in "[0-9]{4}-[0-9]{2}-[0-9]{2}T[0-9]{2}:[0-9]{2}:[0-9]{2}(\\.[0-9]{1,9})?(Z|[+-][0-9]{2}:[0-9]{2})"
+    ) {
+        check(&input);
+    }
+
+    #[test]
+    fn garbage_digits(
+        input in "[0-9a/:]{4}-[0-9a/:]{2}-[0-9a/:]{2}T[0-9a/:]{2}:[0-9a/:]{2}:[0-9a/:]{2}(\\.[0-9]{1,9})?(Z|[+-][0-9a/:]{2}:[0-9a/:]{2})"
+    ) {
+        check(&input);
+    }
+}
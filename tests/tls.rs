@@ -0,0 +1,200 @@
+#![cfg(feature = "tls")]
+
+use std::io::Read;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+use syslog::rfc5424::parse_message;
+use syslog::sender::Sender;
+use syslog::server::{Peer, Received, Server};
+use syslog::tls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use syslog::tls::{Acceptor, Algorithm, Connector, Fingerprint};
+use syslog::Message;
+
+const RAW: &str = r#"<165>1 2003-10-11T22:14:15.003000Z mymachine.example.com evntslog 8710 ID47 [exampleSDID@32473 iut="3"] An application event log entry"#;
+
+fn message() -> Message<String> {
+    parse_message(RAW.as_bytes()).unwrap().into_owned()
+}
+
+struct Identity {
+    cert: CertificateDer<'static>,
+    key: KeyPair,
+    params: CertificateParams,
+}
+
+impl Identity {
+    fn params(name: &str) -> CertificateParams {
+        let mut params = CertificateParams::new(vec![name.to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, name);
+        params
+    }
+
+    fn ca() -> Self {
+        let mut params = Self::params("Example CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.clone().self_signed(&key).unwrap().der().clone();
+        Identity { cert, key, params }
+    }
+
+    fn self_signed(name: &str) -> Self {
+        let params = Self::params(name);
+        let key = KeyPair::generate().unwrap();
+        let cert = params.clone().self_signed(&key).unwrap().der().clone();
+        Identity { cert, key, params }
+    }
+
+    fn issued_by(name: &str, ca: &Identity) -> Self {
+        let params = Self::params(name);
+        let key = KeyPair::generate().unwrap();
+        let issuer = ca.params.clone().self_signed(&ca.key).unwrap();
+        let cert = params
+            .clone()
+            .signed_by(&key, &issuer, &ca.key)
+            .unwrap()
+            .der()
+            .clone();
+        Identity { cert, key, params }
+    }
+
+    fn chain(&self) -> Vec<CertificateDer<'static>> {
+        vec![self.cert.clone()]
+    }
+
+    fn key(&self) -> PrivateKeyDer<'static> {
+        PrivatePkcs8KeyDer::from(self.key.serialize_der()).into()
+    }
+
+    fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(Algorithm::Sha256, &self.cert)
+    }
+}
+
+fn recv(rx: &Receiver<Received>) -> Received {
+    rx.recv_timeout(Duration::from_secs(5)).expect("a message")
+}
+
+#[test]
+fn roots() {
+    let ca = Identity::ca();
+    let server = Identity::issued_by("localhost", &ca);
+
+    let (tx, rx) = mpsc::channel();
+    let acceptor = Acceptor::new(server.chain(), server.key());
+    let listener = Server::new(tx).tls("127.0.0.1:0", &acceptor).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let connector = Connector::new().root_certificates([ca.cert.clone()]);
+    let mut sender = Sender::tls(addr, "localhost", &connector).unwrap();
+    sender.send(&message()).unwrap();
+    sender.send(&message()).unwrap();
+
+    for _ in 0..2 {
+        let received = recv(&rx);
+        assert_eq!(received.message, message());
+        assert!(matches!(received.peer, Peer::Tls { subject: None, .. }));
+    }
+
+    // the certificate must be valid for the name, and issued by a root
+    assert!(Sender::tls(addr, "example.com", &connector).is_err());
+    assert!(Sender::tls(addr, "localhost", &Connector::new()).is_err());
+    let other = Identity::ca();
+    let connector = Connector::new().root_certificates([other.cert.clone()]);
+    assert!(Sender::tls(addr, "localhost", &connector).is_err());
+
+    listener.shutdown();
+}
+
+#[test]
+fn mutual_fingerprints() {
+    let server = Identity::self_signed("logs");
+    let client = Identity::self_signed("web01");
+
+    let (tx, rx) = mpsc::channel();
+    let acceptor = Acceptor::new(server.chain(), server.key()).fingerprint(client.fingerprint());
+    let listener = Server::new(tx).tls("127.0.0.1:0", &acceptor).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // a matching fingerprint is enough, whatever the name
+    let connector = Connector::new()
+        .fingerprint(server.fingerprint())
+        .identity(client.chain(), client.key());
+    let mut sender = Sender::tls(addr, "127.0.0.1", &connector).unwrap();
+    sender.send(&message()).unwrap();
+
+    let received = recv(&rx);
+    assert_eq!(received.message, message());
+    let Peer::Tls { addr, subject } = received.peer else {
+        panic!("{:?}", received.peer);
+    };
+    assert!(addr.ip().is_loopback());
+    assert_eq!(subject.as_deref(), Some("CN=web01"));
+    drop(sender);
+
+    // clients without a certificate, or with an unknown one, are rejected
+    let stranger = Identity::self_signed("stranger");
+    for connector in [
+        Connector::new().fingerprint(server.fingerprint()),
+        Connector::new()
+            .fingerprint(server.fingerprint())
+            .identity(stranger.chain(), stranger.key()),
+    ] {
+        // with TLS 1.3 the client finishes its handshake first
+        if let Ok(mut sender) = Sender::tls(addr, "logs", &connector) {
+            let _ = sender.send(&message());
+        }
+    }
+    assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+    // and clients don't trust other server certificates
+    let connector = Connector::new()
+        .fingerprint(stranger.fingerprint())
+        .identity(client.chain(), client.key());
+    assert!(Sender::tls(addr, "logs", &connector).is_err());
+}
+
+#[test]
+fn client_roots() {
+    let ca = Identity::ca();
+    let server = Identity::self_signed("logs");
+    let client = Identity::issued_by("web02", &ca);
+
+    let (tx, rx) = mpsc::channel();
+    let acceptor = Acceptor::new(server.chain(), server.key()).root_certificates([ca.cert.clone()]);
+    let listener = Server::new(tx).tls("127.0.0.1:0", &acceptor).unwrap();
+
+    let connector = Connector::new()
+        .fingerprint(server.fingerprint())
+        .identity(client.chain(), client.key());
+    let mut sender = Sender::tls(listener.local_addr().unwrap(), "logs", &connector).unwrap();
+    sender.send(&message()).unwrap();
+
+    let received = recv(&rx);
+    assert_eq!(received.message, message());
+    assert!(
+        matches!(received.peer, Peer::Tls { subject: Some(ref subject), .. } if subject == "CN=web02")
+    );
+}
+
+#[test]
+fn handshake_timeout() {
+    let server = Identity::self_signed("logs");
+
+    let (tx, rx) = mpsc::channel();
+    let acceptor = Acceptor::new(server.chain(), server.key());
+    let listener = Server::new(tx)
+        .idle_timeout(Duration::from_millis(300))
+        .tls("127.0.0.1:0", &acceptor)
+        .unwrap();
+
+    // a client which never starts the handshake is dropped
+    let start = Instant::now();
+    let mut conn = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    conn.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    assert!(matches!(conn.read(&mut [0; 1]), Ok(0) | Err(_)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(rx.try_recv().is_err());
+}